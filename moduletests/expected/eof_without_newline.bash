@@ -0,0 +1 @@
+echo "$a" "$b" "$PWD" "$PWD" x"$c"
\ No newline at end of file
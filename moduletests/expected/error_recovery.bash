@@ -0,0 +1,27 @@
+echo "$a"
+echo $10 "$b"
+echo "$PWD" "$c"
+echo "$d $((1 +
+moduletests/original/error_recovery.bash:2:6: Unsupported syntax: Syntactic pitfall
+echo $a
+echo $10 $b
+     ^^^
+This does not mean what it looks like. You may be forgiven to think that the full string of numerals is the variable name. Only the fist is.
+
+Try this and be shocked: f() { echo "$9" "$10"; }; f a b c d e f g h i j
+
+Here is where braces should be used to disambiguate, e.g. "${10}" vs "${1}0".
+
+Syntactic pitfalls are deemed too dangerous to fix automatically
+(the purpose of Shellharden is to fix vulnerable code – code that mostly does what it looks like, as opposed to code that never does what it looks like):
+* Fixing what it does would be 100% subtle and might slip through code review unnoticed.
+* Fixing its look would make a likely bug look intentional.
+
+With --unbreak, it is fixed anyway, with braces around all the digits.
+moduletests/original/error_recovery.bash:5:1: Unexpected end of file
+echo `pwd` $c
+echo "$d $((1 +
+
+^
+The file's end was reached without closing all sytactic scopes.
+Either, the parser got lost, or the file is truncated or malformed.
@@ -1,5 +1,7 @@
 $((
+moduletests/original/error_unexpected_eof_arith.bash:2:1: Unexpected end of file
+$((
 
-moduletests/original/error_unexpected_eof_arith.bash: Unexpected end of file
+^
 The file's end was reached without closing all sytactic scopes.
 Either, the parser got lost, or the file is truncated or malformed.
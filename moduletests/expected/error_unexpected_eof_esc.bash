@@ -1,4 +1,6 @@
 \
-moduletests/original/error_unexpected_eof_esc.bash: Unexpected end of file
+moduletests/original/error_unexpected_eof_esc.bash:1:2: Unexpected end of file
+\
+ ^
 The file's end was reached without closing all sytactic scopes.
 Either, the parser got lost, or the file is truncated or malformed.
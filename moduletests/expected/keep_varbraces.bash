@@ -0,0 +1,3 @@
+echo "$a" "${b}" "${c}" x"${d}"y "$e_" "${f}"_ "$g"
+echo "$PWD" "$PWD" "${PWD}"
+echo ${#a} "${a[@]}" "${a:-x}" "$1" "${1}" "$@" $#
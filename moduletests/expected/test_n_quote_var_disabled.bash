@@ -0,0 +1,3 @@
+[ -n $a ] && [ -z $b ] && echo $c
+[ "$a" != "" ] && [ "$b" = "" ]
+test -n $(date) && test "$(date)" != ""
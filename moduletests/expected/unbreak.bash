@@ -0,0 +1,6 @@
+echo "${10}" "${10}" "${10}" "$1${10}" "${1}0"
+printf '%s\n' "${12}$"
+
+[ "$a" != "" ] && [ "${10}" != "" ]
+test "$a$b" != "" || test "$a" != "" || test "$(date)" != ""
+[ "$a" = "" ]
@@ -1,7 +1,9 @@
 echo above
 echo beyond
-echo 
-moduletests/original/unsupp_numeral_variable_unquot.bash: Unsupported syntax: Syntactic pitfall
+echo $10 ()
+moduletests/original/unsupp_numeral_variable_unquot.bash:3:6: Unsupported syntax: Syntactic pitfall
+echo above
+echo beyond
 echo $10 ()
      ^^^
 This does not mean what it looks like. You may be forgiven to think that the full string of numerals is the variable name. Only the fist is.
@@ -14,3 +16,5 @@ Syntactic pitfalls are deemed too dangerous to fix automatically
 (the purpose of Shellharden is to fix vulnerable code – code that mostly does what it looks like, as opposed to code that never does what it looks like):
 * Fixing what it does would be 100% subtle and might slip through code review unnoticed.
 * Fixing its look would make a likely bug look intentional.
+
+With --unbreak, it is fixed anyway, with braces around all the digits.
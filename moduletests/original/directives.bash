@@ -0,0 +1,16 @@
+# shellharden disable=quote-var
+echo $a `date`
+echo $a `date`
+
+# shellharden disable=backtick,pwd
+echo $b `date` `pwd`
+
+f() {
+	# shellharden disable=all
+	echo $* `pwd`
+	echo $* `pwd`
+}
+
+# shellharden disable-file=dollar-star
+echo $* $c
+g $*
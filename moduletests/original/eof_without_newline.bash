@@ -0,0 +1 @@
+echo $a ${b} `pwd` $(pwd) x${c}
\ No newline at end of file
@@ -0,0 +1,4 @@
+echo $a
+echo $10 $b
+echo `pwd` $c
+echo "$d $((1 +
@@ -0,0 +1,3 @@
+[ -n $a ] && [ -z $b ] && echo $c
+[ -n "$a" ] && [ -z "$b" ]
+test -n `date` && test -n "$(date)"
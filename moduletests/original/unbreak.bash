@@ -0,0 +1,6 @@
+echo $10 ${10} "$10" $1$10 "${1}0"
+printf '%s\n' "$12$"
+
+[ -n $a ] && [ -n $10 ]
+test -n $a$b || test -n "$a" || test -n `date`
+[ -z $a ]
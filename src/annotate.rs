@@ -0,0 +1,85 @@
+/*
+ * Copyright 2024 Andreas Nordal
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+// Footnotes under each changed line of the colored diff, as by --annotate:
+// Carets under the changes, followed by why, for each rule on the line.
+
+use crate::errfmt::caret_line;
+use crate::rule::Rule;
+
+pub struct Notes {
+	// The output line so far, without colors.
+	line: Vec<u8>,
+	// Where on the line each change begins, and by which rule.
+	marks: Vec<(usize, Rule)>,
+	// Edit groups below this are already noted, where they began.
+	noted: usize,
+}
+
+impl Notes {
+	pub fn new() -> Notes {
+		Notes{line: Vec::new(), marks: Vec::new(), noted: 0}
+	}
+	// An edit, about to be written. Only the first edit of each group is marked,
+	// like the opening quote, not the closing one.
+	pub fn mark(&mut self, group: usize, rule: Rule) {
+		if group >= self.noted {
+			self.marks.push((self.line.len(), rule));
+			self.noted = group + 1;
+		}
+	}
+	pub fn extend(&mut self, text: &[u8]) {
+		self.line.extend_from_slice(text);
+	}
+	pub fn is_pending(&self) -> bool {
+		!self.marks.is_empty()
+	}
+	// Ends the line, and returns its footnotes.
+	pub fn end_line(&mut self) -> Vec<u8> {
+		let mut footnotes = Vec::new();
+		for (i, &(_, rule)) in self.marks.iter().enumerate() {
+			if self.marks[.. i].iter().any(|&(_, earlier)| earlier == rule) {
+				continue;
+			}
+			// The marked character is the first of the change; never a tab.
+			let mut begin = 0;
+			for &(pos, _) in self.marks.iter().filter(|&&(_, r)| r == rule) {
+				if pos < begin {
+					continue;
+				}
+				footnotes.extend(caret_line(&self.line[begin .. pos], b"^"));
+				begin = pos + 1;
+			}
+			footnotes.extend_from_slice(format!(" {} [{}]\n", rule.summary(), rule.id()).as_bytes());
+		}
+		self.line.clear();
+		self.marks.clear();
+		footnotes
+	}
+}
+
+#[test]
+fn test_notes() {
+	let mut notes = Notes::new();
+	notes.extend(b"\techo ");
+	notes.mark(0, Rule::Quote);
+	notes.extend(b"\"$a\" ");
+	notes.mark(0, Rule::Quote);
+	notes.mark(1, Rule::Quote);
+	notes.extend(b"\"$b\" ");
+	notes.mark(2, Rule::Backtick);
+	notes.extend(b"$(date)");
+	assert!(notes.is_pending());
+	assert_eq!(
+		String::from_utf8(notes.end_line()).unwrap(),
+		"\t     ^    ^ Quote to prevent word splitting and pathname expansion [quote-var]\n\
+		\t               ^ Use $( ), which nests, instead of backticks [backtick]\n",
+	);
+	assert!(!notes.is_pending());
+	assert!(notes.end_line().is_empty());
+}
@@ -0,0 +1,613 @@
+/*
+ * Copyright 2016 - 2021 Andreas Nordal
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+// The command line interface, which is all there is to the binary.
+
+use std::env;
+use std::io::IsTerminal;
+use std::io::Write;
+use std::process;
+use std::ffi::OsStr;
+use std::ffi::OsString;
+use std::path::Path;
+
+use crate::config;
+use crate::errfmt;
+use crate::errfmt::ContextualError;
+use crate::explain;
+use crate::filestream::os_bytes;
+use crate::ignore;
+use crate::lsp;
+use crate::machine;
+use crate::machine::OutputSelector;
+use crate::parallel;
+use crate::report;
+use crate::rule;
+use crate::sarif;
+use crate::walk;
+
+fn help(configs: &mut config::Configs) {
+	println!(
+		"Shellharden: The corrective bash syntax highlighter.\n\
+		\n\
+		Usage:\n\
+		\tshellharden [options] [files]\n\
+		\tshellharden [options] [directories]\n\
+		\tcat files | shellharden [options] ''\n\
+		\tshellharden [options] -c script\n\
+		\n\
+		Shellharden is a syntax highlighter and a tool to semi-automate the rewriting\n\
+		of scripts to ShellCheck conformance, mainly focused on quoting.\n\
+		\n\
+		The default mode of operation is like `cat`, but with syntax highlighting in\n\
+		foreground colors and suggestive changes in background colors.\n\
+		\n\
+		Directories are searched recursively for files named *.sh or *.bash, and for\n\
+		files without an extension that start with a bash or sh shebang.\n\
+		Symlinked directories and .git directories are skipped.\n\
+		Files listed in a .shellhardenignore file in the file's directory or above,\n\
+		in .gitignore syntax, are skipped too, as are those matching --exclude.\n\
+		\n\
+		Settings are read from the nearest .shellharden.toml file, looking upward\n\
+		from each file's directory: mode = \"check\" (or another mode, as named by\n\
+		its option), unified = N, unbreak = true, color = \"never\" (or another\n\
+		--color), dialect = \"sh\" (no arrays) or \"bash\", exclude = [globs],\n\
+		disable and enable = [rules], and [[override]] tables with a path = glob and\n\
+		settings for files matching it. Options take precedence. Replacing files\n\
+		takes --replace; it is not a mode there. The file that applies here is named\n\
+		at the end of this help text.\n\
+		\n\
+		In a script, the comment # shellharden disable=RULE,... disables rules for\n\
+		the next line of code, also if the comment is after code on its own line,\n\
+		and # shellharden disable-file=RULE,... for the rest of the file.\n\
+		Unknown rules, and directives that disable nothing, are warned about.\n\
+		\n\
+		All files are treated, even after a failure. With more than one, a summary\n\
+		is printed to stderr. The exit status is that of the worst outcome: 3 for\n\
+		usage errors, then 1 for other errors, then 2 for suggested changes.\n\
+		\n\
+		Options:\n\
+		\t--suggest         Output a colored diff suggesting changes.\n\
+		\t--syntax          Output syntax highlighting with ANSI colors.\n\
+		\t--syntax-suggest  Diff with syntax highlighting (default mode).\n\
+		\t--transform       Output suggested changes.\n\
+		\t--check           No output; exit with 2 if changes are suggested, or with 1\n\
+		\t                  if there are syntax errors, which are told on stderr.\n\
+		\t--format=json     Output suggested changes and syntax errors as JSON lines,\n\
+		\t                  each change with the edits it consists of;\n\
+		\t                  exit with 2 if changes are suggested.\n\
+		\t--format=sarif    Like --format=json, but as one SARIF 2.1.0 log for all files.\n\
+		\t--format=gcc      Like --format=json, but as file:line:col: lines for editors.\n\
+		\t--patch           Output suggested changes as a unified diff, for patch -p1;\n\
+		\t                  exit with 2 if changes are suggested.\n\
+		\t--list            Output the names of files that would be changed, one per\n\
+		\t                  line, even with syntax errors elsewhere in the file;\n\
+		\t                  exit with 2 if any would.\n\
+		\t--list0           Like --list, but each name is terminated by NUL.\n\
+		\t--color=WHEN      Use colors always (default), never, or if stdout is a\n\
+		\t                  terminal (auto). Without colors, the diff is a unified diff.\n\
+		\t--annotate        With --suggest or --syntax-suggest, explain the changes\n\
+		\t                  under each changed line, with the id of the rule.\n\
+		\t--unified=N       Lines of context in --patch output (default 3).\n\
+		\t--replace         Replace file contents with suggested changes. Each file is\n\
+		\t                  replaced as a whole, keeping its permissions and owner.\n\
+		\t                  Symlinked files are replaced behind the link; files with\n\
+		\t                  other hard links are left alone with an error.\n\
+		\t--backup[=SUFFIX] With --replace, keep the original of each changed file\n\
+		\t                  next to it, named with SUFFIX added (default .orig).\n\
+		\t--keep-varbraces  Don't remove braces around variable names, like --disable\n\
+		\t                  varbrace. To also add them, --enable add-varbraces.\n\
+		\t--unbreak         Also fix things that never work, like $10 → ${{10}}, instead\n\
+		\t                  of failing. This changes what the script does, so these\n\
+		\t                  changes are highlighted in yellow.\n\
+		\t-c STRING         Treat STRING as a script, named -c in messages.\n\
+		\t--exclude GLOB    Skip files and directories that match GLOB.\n\
+		\t--include GLOB    Only treat files that match GLOB. In directories, this\n\
+		\t                  replaces recognizing scripts by name and shebang.\n\
+		\t--disable RULE    Don't suggest changes by the rules given by id, separated\n\
+		\t                  by commas, or all. See --list-rules for the ids.\n\
+		\t--enable RULE     The opposite. Only add-varbraces is not enabled by default.\n\
+		\t--list-rules      List the rules and syntax errors by id, with a summary.\n\
+		\t--explain ID      Explain why a rule or syntax error exists, with examples,\n\
+		\t                  and when the change makes the script do something else.\n\
+		\t-j N              Treat N files at a time. The output is the same.\n\
+		\t--lsp             Run as a language server on stdin and stdout.\n\
+		\t--                Don't treat further arguments as options.\n\
+		\t-h|--help         Show help text.\n\
+		\t--version         Show version.\n\
+		\n\
+		The changes suggested by Shellharden inhibits word splitting and indirect\n\
+		pathname expansion. This will make your script ShellCheck compliant in terms of\n\
+		quoting. Whether your script will work afterwards is a different question:\n\
+		If your script was using those features on purpose, it obviously won't anymore!\n\
+		\n\
+		Every script is possible to write without using word splitting or indirect\n\
+		pathname expansion, but it may involve doing things differently.\n\
+		See the accompanying file how_to_do_things_safely_in_bash.md or online:\n\
+		https://github.com/anordal/shellharden/blob/master/how_to_do_things_safely_in_bash.md\n\
+		"
+	);
+	match configs.current() {
+		Some(path) => println!("Settings here are read from {}", path.display()),
+		None => println!("Settings here are not read from any {} file.", config::CONFIGFILE),
+	}
+}
+
+pub fn main() {
+	let mut args: std::env::ArgsOs = env::args_os();
+	args.next();
+
+	let mut sett = machine::Settings::default();
+	let mut explicit = config::Explicit::default();
+	let mut configs = config::Configs::new();
+
+	let mut sarif_results: Option<Vec<u8>> = None;
+	let mut jobs: usize = 1;
+	let mut queue: Vec<Task> = Vec::new();
+	let mut summary = Summary::default();
+	let mut exit_code: i32 = 0;
+	let mut opt_trigger: &str = "-";
+	let mut filter = ignore::Filter::new();
+	// An option that takes the next argument.
+	let mut pending: Option<&'static str> = None;
+	'args: for arg in args {
+		let sources: Vec<Result<Source, (OsString, std::io::Error)>> = if let Some(option) = pending.take() {
+			match option {
+				"--exclude" => {
+					filter.exclude(&arg.to_string_lossy());
+					continue;
+				}
+				"--include" => {
+					filter.include(&arg.to_string_lossy());
+					continue;
+				}
+				"--explain" => {
+					flush(&mut queue, jobs, &mut sarif_results, &mut summary);
+					match explain::explain(&arg.to_string_lossy()) {
+						Some(text) => println!("{}", text),
+						None => {
+							errfmt::blame_path(&arg, "No such rule or syntax error.");
+							exit_code = 3;
+							break 'args;
+						}
+					}
+					continue;
+				}
+				"--enable" | "--disable" => {
+					for id in arg.to_string_lossy().split(',') {
+						match rule::select(id) {
+							Some(rules) => {
+								for rule in rules {
+									sett.enable(rule, option == "--enable");
+									explicit.rules.push(rule);
+								}
+							}
+							None => {
+								flush(&mut queue, jobs, &mut sarif_results, &mut summary);
+								errfmt::blame_path(&OsString::from(id), "No such rule.");
+								exit_code = 3;
+								break 'args;
+							}
+						}
+					}
+					continue;
+				}
+				"-j" => {
+					match arg.to_str().and_then(|n| n.parse::<usize>().ok()) {
+						Some(n) if n > 0 => jobs = n,
+						_ => {
+							flush(&mut queue, jobs, &mut sarif_results, &mut summary);
+							errfmt::blame_path(&arg, "Not a number of jobs.");
+							exit_code = 3;
+							break;
+						}
+					}
+					continue;
+				}
+				_ => vec![Ok(Source::Inline(arg))],
+			}
+		} else if let Some(option) = get_if_opt(&arg, opt_trigger) {
+			match option {
+				"--suggest" | "--syntax" | "--syntax-suggest" | "--transform" | "--check" | "--patch" | "--replace" | "--list" | "--list0" => {
+					sett.set_mode(&option["--".len() ..]);
+					explicit.mode = true;
+				}
+				"--format=json" | "--format=sarif" | "--format=gcc" => {
+					sett.set_mode(&option["--format=".len() ..]);
+					explicit.mode = true;
+				}
+				"--keep-varbraces" => {
+					sett.enable(rule::Rule::VarBrace, false);
+					explicit.rules.push(rule::Rule::VarBrace);
+				}
+				"--annotate" => {
+					sett.annotate = true;
+				}
+				"--backup" => {
+					sett.backup = Some(String::from(".orig"));
+				}
+				"--unbreak" => {
+					sett.unbreak = true;
+					explicit.unbreak = true;
+				}
+				"--lsp" => {
+					flush(&mut queue, jobs, &mut sarif_results, &mut summary);
+					exit_code = match lsp::serve(&sett) {
+						Ok(true) => 0,
+						Ok(false) => 1,
+						Err(e) => {
+							eprintln!("Language server: {}", e);
+							1
+						}
+					};
+					break;
+				}
+				"--help" | "-h" => {
+					flush(&mut queue, jobs, &mut sarif_results, &mut summary);
+					help(&mut configs);
+				}
+				"--list-rules" => {
+					flush(&mut queue, jobs, &mut sarif_results, &mut summary);
+					print!("{}", explain::list());
+				}
+				"--version" => {
+					flush(&mut queue, jobs, &mut sarif_results, &mut summary);
+					println!(env!("CARGO_PKG_VERSION"));
+				}
+				"-c" => {
+					pending = Some(INLINE_NAME);
+				}
+				"--exclude" => {
+					pending = Some("--exclude");
+				}
+				"--include" => {
+					pending = Some("--include");
+				}
+				"--enable" => {
+					pending = Some("--enable");
+				}
+				"--explain" => {
+					pending = Some("--explain");
+				}
+				"--disable" => {
+					pending = Some("--disable");
+				}
+				"-j" => {
+					pending = Some("-j");
+				}
+				"--" => {
+					opt_trigger = "\x00";
+				}
+				_ if option.starts_with("--backup=") => {
+					let suffix = &option["--backup=".len() ..];
+					if suffix.is_empty() || suffix.contains('/') {
+						flush(&mut queue, jobs, &mut sarif_results, &mut summary);
+						errfmt::blame_path(&arg, "Not a file name suffix.");
+						exit_code = 3;
+						break;
+					}
+					sett.backup = Some(String::from(suffix));
+				}
+				_ if option.starts_with("--color=") => {
+					if !sett.set_color(&option["--color=".len() ..]) {
+						flush(&mut queue, jobs, &mut sarif_results, &mut summary);
+						errfmt::blame_path(&arg, "Not auto, always or never.");
+						exit_code = 3;
+						break;
+					}
+					explicit.color = true;
+				}
+				_ if option.starts_with("--unified=") => {
+					if let Ok(n) = option["--unified=".len() ..].parse::<usize>() {
+						sett.patch_context = n;
+						explicit.unified = true;
+					} else {
+						flush(&mut queue, jobs, &mut sarif_results, &mut summary);
+						errfmt::blame_path(&arg, "Not a number of lines.");
+						exit_code = 3;
+						break;
+					}
+				}
+				_ => {
+					flush(&mut queue, jobs, &mut sarif_results, &mut summary);
+					errfmt::blame_path(&arg, "No such option.");
+					exit_code = 3;
+					break;
+				}
+			}
+			continue;
+		} else if is_directory(&arg) {
+			if filter.is_excluded(Path::new(&arg), true) {
+				continue;
+			}
+			walk::find_scripts(&arg, &mut filter).into_iter().map(|found| found.map(Source::File)).collect()
+		} else {
+			let path = Path::new(&arg);
+			if !arg.is_empty() && (filter.is_excluded(path, false) || !filter.is_included(path)) {
+				continue;
+			}
+			vec![Ok(Source::File(arg))]
+		};
+		if sett.backup.is_some() && !sett.replace {
+			flush(&mut queue, jobs, &mut sarif_results, &mut summary);
+			errfmt::blame_path(&OsString::from("--backup"), "Only with --replace.");
+			exit_code = 3;
+			break;
+		}
+		for found in sources {
+			let (sett, found) = configure(&mut configs, &sett, &explicit, found);
+			if jobs > 1 {
+				queue.push(Task{sett, found});
+				continue;
+			}
+			let mut warnings = Vec::new();
+			let (name, (change, res)) = match found {
+				Ok(source) => {
+					let buffer = if sett.osel == OutputSelector::Sarif {
+						Some(sarif_results.get_or_insert_with(Vec::new))
+					} else {
+						None
+					};
+					(source.name(), treat(&source, &sett, buffer, &mut warnings))
+				}
+				Err((path, e)) => (path, (false, Err(machine::Error::Stdio(e)))),
+			};
+			summary.add(&name, sett.osel, &warnings, change, res);
+		}
+	}
+	flush(&mut queue, jobs, &mut sarif_results, &mut summary);
+	if summary.treated > 1 {
+		summary.print();
+	}
+	if let Some(option) = pending {
+		errfmt::blame_path(&OsString::from(option), "Missing argument.");
+		exit_code = 3;
+	} else if sett.backup.is_some() && !sett.replace && exit_code != 3 {
+		errfmt::blame_path(&OsString::from("--backup"), "Only with --replace.");
+		exit_code = 3;
+	}
+	if let Some(results) = sarif_results {
+		let mut log = Vec::<u8>::new();
+		sarif::write_log(&mut log, &results);
+		if let Err(e) = std::io::stdout().lock().write_all(&log) {
+			eprintln!("Unable to write to stdout: {}", e);
+			exit_code = 1;
+		}
+	}
+	process::exit(worst(exit_code, summary.exit_code));
+}
+
+// The name of a script given with -c, in messages.
+const INLINE_NAME: &str = "-c";
+
+enum Source {
+	// A file, or stdin if the path is empty.
+	File(OsString),
+	Inline(OsString),
+}
+
+impl Source {
+	fn name(&self) -> OsString {
+		match self {
+			Source::File(path) => path.clone(),
+			Source::Inline(_) => OsString::from(INLINE_NAME),
+		}
+	}
+}
+
+// A source, or a failure to find it, with the settings that apply to it.
+struct Task {
+	sett: machine::Settings,
+	found: Result<Source, (OsString, std::io::Error)>,
+}
+
+// The settings for a source, after its configuration file.
+// A broken configuration file is a failure to treat the source.
+fn configure(
+	configs: &mut config::Configs,
+	sett: &machine::Settings,
+	explicit: &config::Explicit,
+	found: Result<Source, (OsString, std::io::Error)>,
+) -> (machine::Settings, Result<Source, (OsString, std::io::Error)>) {
+	let path = match &found {
+		Ok(Source::File(path)) => Path::new(path),
+		Ok(Source::Inline(_)) => Path::new(""),
+		Err(_) => return (sett.clone(), found),
+	};
+	match configs.settings_for(path, sett, explicit) {
+		Ok(sett) => (colored(sett), found),
+		Err(msg) => {
+			let name = found.as_ref().ok().unwrap().name();
+			(sett.clone(), Err((name, std::io::Error::new(std::io::ErrorKind::InvalidData, msg))))
+		}
+	}
+}
+
+// Output goes to stdout, or to the buffer if given.
+// Returns whether changes are suggested, also if there are errors, and the result.
+fn treat(
+	source: &Source,
+	sett: &machine::Settings,
+	buffer: Option<&mut Vec<u8>>,
+	warnings: &mut Vec<ContextualError>,
+) -> (bool, Result<(), machine::Error>) {
+	match (sett.osel, source, buffer) {
+		// Without colors, the diff is a unified diff, which is no failure in this mode.
+		(OutputSelector::Diff, source, buffer) if sett.color == Some(false) => {
+			let patch = machine::Settings {
+				osel: OutputSelector::Patch,
+				..sett.clone()
+			};
+			match treat(source, &patch, buffer, warnings) {
+				(change, Err(machine::Error::Check)) => (change, Ok(())),
+				result => result,
+			}
+		}
+		(osel, source, Some(out)) if osel.is_report() => report(source, sett, out, warnings),
+		(osel, source, None) if osel.is_report() => {
+			let mut out = Vec::<u8>::new();
+			let (change, res) = report(source, sett, &mut out, warnings);
+			match std::io::stdout().lock().write_all(&out) {
+				Ok(()) => (change, res),
+				Err(e) => (change, Err(machine::Error::Stdio(e))),
+			}
+		}
+		(_, Source::File(path), buffer) => machine::treatfile(path, sett, buffer, warnings),
+		(_, Source::Inline(script), buffer) => {
+			machine::treatstring(&os_bytes(script), sett, buffer, warnings)
+		}
+	}
+}
+
+// Decides whether to use colors. Without them, the syntax is not highlighted.
+fn colored(mut sett: machine::Settings) -> machine::Settings {
+	let color = sett.color.unwrap_or_else(|| std::io::stdout().is_terminal());
+	sett.color = Some(color);
+	sett.syntax &= color;
+	sett
+}
+
+// Treat the queued sources in parallel, and print the results in order.
+fn flush(
+	queue: &mut Vec<Task>,
+	jobs: usize,
+	sarif_results: &mut Option<Vec<u8>>,
+	summary: &mut Summary,
+) {
+	parallel::run_ordered(std::mem::take(queue), jobs, |task| {
+		let mut out = Vec::<u8>::new();
+		let mut warnings = Vec::new();
+		let osel = task.sett.osel;
+		match task.found {
+			Ok(source) => {
+				let (change, res) = treat(&source, &task.sett, Some(&mut out), &mut warnings);
+				(source.name(), osel, out, warnings, change, res)
+			}
+			Err((path, e)) => (path, osel, out, warnings, false, Err(machine::Error::Stdio(e))),
+		}
+	}, |(name, osel, out, warnings, change, res)| {
+		let res = if osel == OutputSelector::Sarif {
+			let results = sarif_results.get_or_insert_with(Vec::new);
+			if !results.is_empty() && !out.is_empty() {
+				results.extend_from_slice(b",\n");
+			}
+			results.extend_from_slice(&out);
+			res
+		} else {
+			match std::io::stdout().lock().write_all(&out) {
+				Ok(()) => res,
+				Err(e) => Err(machine::Error::Stdio(e)),
+			}
+		};
+		summary.add(&name, osel, &warnings, change, res);
+		true
+	});
+}
+
+// What became of the sources treated, for the summary and the exit code.
+#[derive(Default)]
+struct Summary {
+	treated: usize,
+	changed: usize,
+	syntax: usize,
+	io: usize,
+	exit_code: i32,
+}
+
+impl Summary {
+	fn add(
+		&mut self,
+		name: &OsString,
+		osel: OutputSelector,
+		warnings: &[ContextualError],
+		change: bool,
+		res: Result<(), machine::Error>,
+	) {
+		self.treated += 1;
+		// Told like syntax errors, but without consequence.
+		errfmt::blame_syntax(name, warnings);
+		// Changes are counted also where there are errors.
+		self.changed += change as usize;
+		let exit_code = match res {
+			Ok(()) => 0,
+			Err(e) => {
+				match e {
+					machine::Error::Check => {}
+					machine::Error::Syntax(_) => self.syntax += 1,
+					machine::Error::Stdio(_) => self.io += 1,
+				}
+				blame(name, osel, e)
+			}
+		};
+		self.exit_code = worst(self.exit_code, exit_code);
+	}
+	fn print(&self) {
+		eprintln!(
+			"Treated {} files: {} with changes, {} with syntax errors, {} with I/O errors.",
+			self.treated, self.changed, self.syntax, self.io,
+		);
+	}
+}
+
+// Errors are worse than changes, and usage errors are the worst.
+fn worst(a: i32, b: i32) -> i32 {
+	let rank = |exit_code| match exit_code {
+		0 => 0,
+		2 => 1,
+		1 => 2,
+		_ => 3,
+	};
+	if rank(b) > rank(a) { b } else { a }
+}
+
+// Returns the exit code.
+fn blame(name: &OsString, osel: OutputSelector, e: machine::Error) -> i32 {
+	match (osel, e) {
+		(_, machine::Error::Stdio(ref fail)) => {
+			errfmt::blame_path_io(name, fail);
+			1
+		}
+		(OutputSelector::Json | OutputSelector::Sarif | OutputSelector::Gcc, machine::Error::Syntax(_)) => {
+			// Reported as part of the output.
+			1
+		}
+		(_, machine::Error::Syntax(ref fails)) => {
+			errfmt::blame_syntax(name, fails);
+			1
+		}
+		(_, machine::Error::Check) => 2,
+	}
+}
+
+fn report(
+	source: &Source,
+	sett: &machine::Settings,
+	out: &mut Vec<u8>,
+	warnings: &mut Vec<ContextualError>,
+) -> (bool, Result<(), machine::Error>) {
+	match source {
+		Source::File(path) => report::treatfile(path, sett, out, warnings),
+		Source::Inline(script) => {
+			report::treatstring(OsStr::new(INLINE_NAME), &os_bytes(script), sett, out, warnings)
+		}
+	}
+}
+
+// Directories are searched for scripts. Stdin, named by the empty string, is not one.
+fn is_directory(path: &OsString) -> bool {
+	std::fs::metadata(path).map(|m| m.is_dir()).unwrap_or(false)
+}
+
+fn get_if_opt<'a>(arg: &'a OsStr, opt_trigger: &str) -> Option<&'a str> {
+	if let Some(comparable) = arg.to_str() {
+		if comparable.starts_with(opt_trigger) {
+			return Some(comparable);
+		}
+	}
+	None
+}
@@ -7,7 +7,9 @@
  */
 
 use crate::situation::Horizon;
+use crate::situation::alt;
 use crate::situation::WhatNow;
+use crate::situation::Transition;
 use crate::situation::flush;
 use crate::situation::if_needed;
 use crate::situation::pop;
@@ -18,6 +20,8 @@ use crate::situation::COLOR_SQ;
 use crate::situation::COLOR_VAR;
 use crate::situation::COLOR_ESC;
 
+use crate::rule::Rule;
+
 use crate::microparsers::prefixlen;
 use crate::microparsers::predlen;
 use crate::microparsers::identifierlen;
@@ -143,19 +147,40 @@ pub fn common_token(
 	end_trigger :u16,
 	horizon :Horizon,
 	i :usize,
+) -> Option<WhatNow> {
+	common_token_quoted_by(Rule::Quote, end_trigger, horizon, i)
+}
+
+// Like common_token, with the quotes it adds attributed to the given rule.
+pub fn common_token_quoted_by(
+	rule :Rule,
+	end_trigger :u16,
+	horizon :Horizon,
+	i :usize,
 ) -> Option<WhatNow> {
 	if let Some(res) = find_usual_suspects(end_trigger, horizon, i, true) {
 		return Some(res);
 	}
+	let push_phantom = || push(
+		(i, 0, Some(alt(rule, b"\""))),
+		Box::new(SitStrPhantom {
+			cmd_end_trigger: end_trigger,
+			rule,
+		}),
+	);
 	match common_str_cmd(horizon, i, QuotingCtx::Need) {
 		CommonStrCmdResult::None => None,
 		CommonStrCmdResult::Some(x) => Some(x),
-		CommonStrCmdResult::OnlyWithQuotes(_) => Some(push(
-			(i, 0, Some(b"\"")),
-			Box::new(SitStrPhantom {
-				cmd_end_trigger: end_trigger,
-			}),
-		)),
+		// Not quoted before it is known whether the error is fixed:
+		// Then, the fix begins with the quotes, and the string meets the error again.
+		CommonStrCmdResult::OnlyWithQuotes(WhatNow {
+			transform,
+			transition: Transition::Err(mut e),
+		}) => {
+			e.fix = e.fix.map(|(fixrule, _)| (fixrule, Box::new(push_phantom())));
+			Some(WhatNow { transform, transition: Transition::Err(e) })
+		}
+		CommonStrCmdResult::OnlyWithQuotes(_) => Some(push_phantom()),
 	}
 }
 
@@ -195,7 +220,7 @@ pub fn common_token_quoting_unneeded(
 		CommonStrCmdResult::OnlyWithQuotes(x) => {
 			let (_, len, alt) = x.transform;
 			if let Some(replacement) = alt {
-				if replacement.len() >= len {
+				if replacement.text.len() >= len {
 					#[allow(clippy::collapsible_if)] // Could be expanded.
 					if horizon.input[i] == b'`' {
 						return Some(push(
@@ -266,7 +291,7 @@ fn find_usual_suspects(
 			return Some(push((i, 2, None), Box::new(SitStrSqEsc {})));
 		} else if b == b'*' {
 			// $* → "$@" but not "$*" → "$@"
-			return Some(push_replaceable(COLOR_VAR, i, 2, if_needed(quoting_needed, b"\"$@\"")));
+			return Some(push_replaceable(COLOR_VAR, i, 2, if_needed(quoting_needed, alt(Rule::DollarStar, b"\"$@\""))));
 		}
 	}
 	let (ate, delimiter) = find_heredoc(&horizon.input[i ..]);
@@ -289,7 +314,7 @@ fn find_usual_suspects(
 }
 
 fn push_comment(pre: usize) -> WhatNow {
-	push((pre, 1, None), Box::new(SitComment {}))
+	push((pre, 1, None), Box::new(SitComment { directive: None, len: 0 }))
 }
 
 #[derive(PartialEq)]
@@ -10,12 +10,15 @@ use crate::situation::Horizon;
 use crate::situation::Transition;
 use crate::situation::UnsupportedSyntax;
 use crate::situation::WhatNow;
+use crate::situation::alt;
 use crate::situation::flush;
 use crate::situation::push;
 use crate::situation::if_needed;
 use crate::situation::COLOR_ESC;
 use crate::situation::COLOR_VAR;
 
+use crate::rule::Rule;
+
 use crate::microparsers::predlen;
 use crate::microparsers::is_identifierhead;
 use crate::microparsers::is_identifiertail;
@@ -23,6 +26,7 @@ use crate::microparsers::identifierlen;
 
 
 use crate::sitcmd::SitNormal;
+use crate::sitextent::SitExtent;
 use crate::sitextent::push_extent;
 use crate::sitextent::push_replaceable;
 use crate::sitmagic::push_magic;
@@ -62,10 +66,10 @@ pub fn common_str_cmd(
 			}
 		}
 		return CommonStrCmdResult::OnlyWithQuotes(push(
-			(i, 1, Some(b"$(")),
+			(i, 1, Some(alt(Rule::Backtick, b"$("))),
 			Box::new(SitNormal {
 				end_trigger: u16::from(b'`'),
-				end_replace: Some(b")"),
+				end_replace: Some(alt(Rule::Backtick, b")")),
 			}),
 		));
 	}
@@ -107,6 +111,9 @@ pub fn common_str_cmd(
 		return CommonStrCmdResult::Some(push_extent(COLOR_VAR, i, 2));
 	} else if c == b'@' || c == b'*' || c == b'-' || is_decimal(c) {
 		let digitlen = predlen(is_decimal, &horizon.input[i+1 ..]);
+		if i + 1 + digitlen == horizon.input.len() && (i > 0 || horizon.is_lengthenable) {
+			return CommonStrCmdResult::Some(flush(i));
+		}
 		if digitlen > 1 {
 			return bail_doubledigit(i, 1 + digitlen);
 		}
@@ -120,17 +127,20 @@ pub fn common_str_cmd(
 				if i > 0 || horizon.is_lengthenable {
 					return CommonStrCmdResult::Some(flush(i));
 				}
-				tailhazard = true;
+				// Unless nothing follows.
+				tailhazard = !horizon.is_eof;
 			} else {
 				tailhazard = is_identifiertail(cand[pos_hazard]);
 			}
 		} else {
 			tailhazard = false;
 		}
+		// Braces are needed for the quotes, or wanted for consistency.
+		let rule = if tailhazard { Rule::Quote } else { Rule::AddVarBraces };
 		return CommonStrCmdResult::OnlyWithQuotes(push(
-			(i, 1, if_needed(tailhazard, b"${")),
+			(i, 1, Some(alt(rule, b"${"))),
 			Box::new(SitVarIdent {
-				end_insert: if_needed(tailhazard, b"}"),
+				end_insert: Some(alt(rule, b"}")),
 			}),
 		));
 	} else if c == b'{' {
@@ -138,18 +148,18 @@ pub fn common_str_cmd(
 		let (idlen, pos_hazard) = pos_tailhazard(cand, b'}');
 		let mut rm_braces = false;
 		let mut is_number = false;
-		if pos_hazard == cand.len() {
+		if pos_hazard == cand.len() && !(horizon.is_eof && idlen < pos_hazard) {
 			if i > 0 || horizon.is_lengthenable {
 				return CommonStrCmdResult::Some(flush(i));
 			}
 		} else if idlen == 0 {
 			is_number = is_variable_of_numeric_content(cand[0]);
-		} else if idlen < pos_hazard && !is_identifiertail(cand[pos_hazard]) {
+		} else if idlen < pos_hazard && !is_tailhazard(cand, pos_hazard) {
 			let is_interpolation = is_interpolation || pos_hazard - idlen == 1;
 			rm_braces = need_quotes || !is_interpolation;
 		}
 		let wn = push(
-			(i, 2, if_needed(rm_braces, b"$")),
+			(i, 2, if_needed(rm_braces, alt(Rule::VarBrace, b"$"))),
 			Box::new(SitVarBrace::new(rm_braces, need_quotes)),
 		);
 		return if is_number {
@@ -169,18 +179,18 @@ fn find_pwd(
 ) -> CommonStrCmdResult {
 	let cand: &[u8] = &horizon.input[i + candidate_offset ..];
 	let (idlen, pos_hazard) = pos_tailhazard(cand, end);
-	if pos_hazard == cand.len() {
+	if pos_hazard == cand.len() && !(horizon.is_eof && idlen < pos_hazard) {
 		if i > 0 || horizon.is_lengthenable {
 			return CommonStrCmdResult::Some(flush(i));
 		}
 	} else if idlen == 3 && pos_hazard >= 4 && cand[.. 3].eq(b"pwd") {
-		let tailhazard = is_identifiertail(cand[pos_hazard]);
+		let tailhazard = is_tailhazard(cand, pos_hazard);
 		let replacement: &'static [u8] = if tailhazard {
 			b"${PWD}"
 		} else {
 			b"$PWD"
 		};
-		let what = push_replaceable(COLOR_VAR, i, candidate_offset + idlen + 1, Some(replacement));
+		let what = push_replaceable(COLOR_VAR, i, candidate_offset + idlen + 1, Some(alt(Rule::Pwd, replacement)));
 		return CommonStrCmdResult::OnlyWithQuotes(what);
 	}
 	CommonStrCmdResult::None
@@ -196,6 +206,11 @@ fn pos_tailhazard(horizon: &[u8], end: u8) -> (usize, usize) {
 	(idlen, pos)
 }
 
+// Whether what follows would continue the name. The end of input would not.
+fn is_tailhazard(cand: &[u8], pos_hazard: usize) -> bool {
+	pos_hazard < cand.len() && is_identifiertail(cand[pos_hazard])
+}
+
 fn is_decimal(byte: u8) -> bool {
 	byte.is_ascii_digit()
 }
@@ -204,10 +219,20 @@ fn is_variable_of_numeric_content(c: u8) -> bool {
 	matches!(c, b'#' | b'?' | b'$' | b'!')
 }
 
+// The fix is to take all the digits, which are likely meant.
 fn bail_doubledigit(pos: usize, len: usize) -> CommonStrCmdResult {
-	CommonStrCmdResult::Some(WhatNow {
+	let fix = push(
+		(pos, 1, Some(alt(Rule::PositionalBraces, b"${"))),
+		Box::new(SitExtent {
+			len: len - 1,
+			color: COLOR_VAR,
+			end_insert: Some(alt(Rule::PositionalBraces, b"}")),
+		}),
+	);
+	CommonStrCmdResult::OnlyWithQuotes(WhatNow {
 		transform: (pos, len, None),
 		transition: Transition::Err(UnsupportedSyntax {
+			id: "doubledigit",
 			typ: "Unsupported syntax: Syntactic pitfall",
 			msg: "This does not mean what it looks like. You may be forgiven to think that the full string of \
 			numerals is the variable name. Only the fist is.\n\
@@ -222,7 +247,10 @@ fn bail_doubledigit(pos: usize, len: usize) -> CommonStrCmdResult {
 			does what it looks like, as opposed to code that never does what it looks like):\n\
 			* Fixing what it does would be 100% subtle \
 			and might slip through code review unnoticed.\n\
-			* Fixing its look would make a likely bug look intentional."
+			* Fixing its look would make a likely bug look intentional.\n\
+			\n\
+			With --unbreak, it is fixed anyway, with braces around all the digits.",
+			fix: Some((Rule::PositionalBraces, Box::new(fix))),
 		}),
 	})
 }
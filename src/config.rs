@@ -0,0 +1,344 @@
+/*
+ * Copyright 2024 Andreas Nordal
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+// Project configuration: The nearest .shellharden.toml, looking upward from a file's directory.
+//
+//	mode = "check"           # Like the option --check. Also: suggest, syntax, syntax-suggest,
+//	                         # transform, patch, list, list0, json, sarif, gcc.
+//	unified = 5              # Like --unified=5.
+//	unbreak = true           # Like --unbreak.
+//	color = "never"          # Like --color=never. Also: auto, always.
+//	dialect = "sh"           # Or bash. For sh, bash arrays are not suggested (for-in-array).
+//	exclude = ["vendor/"]    # Like lines of a .shellhardenignore file here.
+//	disable = ["all"]        # Like --disable, for each rule id, before
+//	enable = ["quote-var"]   # enabling these.
+//
+//	[[override]]             # For some paths, relative to this directory:
+//	path = "legacy/"         # One pattern, or a list, like in .shellhardenignore.
+//	mode = "suggest"
+//
+// Options given on the command line take precedence.
+//
+// Replacing files is not a mode here: A checked in file must not make
+// a plain run of shellharden write to files. That takes --replace.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::ignore::normalize;
+use crate::ignore::Pattern;
+use crate::json::Value;
+use crate::machine::Settings;
+use crate::rule;
+use crate::rule::Rule;
+use crate::toml;
+
+pub const CONFIGFILE: &str = ".shellharden.toml";
+
+// Which settings were given on the command line.
+#[derive(Clone, Default)]
+pub struct Explicit {
+	pub mode: bool,
+	pub unified: bool,
+	pub unbreak: bool,
+	pub color: bool,
+	// Rules enabled or disabled.
+	pub rules: Vec<Rule>,
+}
+
+#[derive(Default)]
+struct Options {
+	mode: Option<String>,
+	unified: Option<usize>,
+	unbreak: Option<bool>,
+	color: Option<String>,
+	sh: Option<bool>,
+	disable: Vec<Rule>,
+	enable: Vec<Rule>,
+}
+
+impl Options {
+	fn apply(&self, sett: &mut Settings, explicit: &Explicit) {
+		if let (Some(mode), false) = (&self.mode, explicit.mode) {
+			sett.set_mode(mode);
+		}
+		if let (Some(n), false) = (self.unified, explicit.unified) {
+			sett.patch_context = n;
+		}
+		if let (Some(unbreak), false) = (self.unbreak, explicit.unbreak) {
+			sett.unbreak = unbreak;
+		}
+		if let (Some(when), false) = (&self.color, explicit.color) {
+			sett.set_color(when);
+		}
+		if let Some(sh) = self.sh {
+			sett.sh = sh;
+		}
+		let disable = self.disable.iter().map(|&rule| (rule, false));
+		let enable = self.enable.iter().map(|&rule| (rule, true));
+		for (rule, enabled) in disable.chain(enable) {
+			if !explicit.rules.contains(&rule) {
+				sett.enable(rule, enabled);
+			}
+		}
+	}
+
+	// Returns whether the key is an option.
+	fn set(&mut self, key: &str, value: &Value) -> Result<bool, String> {
+		match key {
+			"mode" => {
+				let mode = value.as_str().ok_or("mode: Expected a string")?;
+				if mode == "replace" {
+					return Err(String::from("mode: Replacing files takes the option --replace"));
+				}
+				if !Settings::default().set_mode(mode) {
+					return Err(format!("mode: No such mode: {}", mode));
+				}
+				self.mode = Some(mode.to_owned());
+			}
+			"unified" => {
+				self.unified = Some(value.as_uint().ok_or("unified: Expected a number of lines")?);
+			}
+			"unbreak" => {
+				self.unbreak = Some(value.as_bool().ok_or("unbreak: Expected true or false")?);
+			}
+			"dialect" => {
+				self.sh = Some(match value.as_str().ok_or("dialect: Expected a string")? {
+					"bash" => false,
+					"sh" => true,
+					dialect => return Err(format!("dialect: Only bash and sh are supported, not {}", dialect)),
+				});
+			}
+			"color" => {
+				let when = value.as_str().ok_or("color: Expected a string")?;
+				if !Settings::default().set_color(when) {
+					return Err(format!("color: Not auto, always or never: {}", when));
+				}
+				self.color = Some(when.to_owned());
+			}
+			"disable" => self.disable = rules(key, value)?,
+			"enable" => self.enable = rules(key, value)?,
+			_ => return Ok(false),
+		}
+		Ok(true)
+	}
+}
+
+struct Config {
+	options: Options,
+	// Later ones take precedence.
+	overrides: Vec<(Vec<Pattern>, Options)>,
+}
+
+fn parse(text: &[u8]) -> Result<(Config, Vec<Pattern>), String> {
+	let value = toml::parse(text).map_err(|(line, msg)| format!("Line {}: {}", line, msg))?;
+	let mut config = Config{options: Options::default(), overrides: Vec::new()};
+	let mut exclude = Vec::new();
+	let members = match value {
+		Value::Object(members) => members,
+		_ => Vec::new(),
+	};
+	for (key, value) in &members {
+		match key.as_str() {
+			"exclude" => exclude = patterns(key, value)?,
+			"override" => {
+				for table in value.as_array() {
+					let mut paths = None;
+					let mut options = Options::default();
+					if let Value::Object(members) = table {
+						for (key, value) in members {
+							if key == "path" {
+								paths = Some(patterns(key, value)?);
+							} else if !options.set(key, value)? {
+								return Err(format!("override: No such key: {}", key));
+							}
+						}
+					}
+					let paths = paths.ok_or("override: Missing path")?;
+					config.overrides.push((paths, options));
+				}
+			}
+			_ => {
+				if !config.options.set(key, value)? {
+					return Err(format!("No such key: {}", key));
+				}
+			}
+		}
+	}
+	Ok((config, exclude))
+}
+
+// One pattern, or a list.
+fn patterns(key: &str, value: &Value) -> Result<Vec<Pattern>, String> {
+	let list = match value {
+		Value::Array(list) => &list[..],
+		value => std::slice::from_ref(value),
+	};
+	list.iter().map(|pattern| {
+		pattern.as_str().map(|p| Pattern::parse(p.as_bytes())).ok_or(format!("{}: Expected strings", key))
+	}).filter_map(Result::transpose).collect()
+}
+
+// One rule id, or a list.
+fn rules(key: &str, value: &Value) -> Result<Vec<Rule>, String> {
+	let list = match value {
+		Value::Array(list) => &list[..],
+		value => std::slice::from_ref(value),
+	};
+	let mut rules = Vec::new();
+	for id in list {
+		let id = id.as_str().ok_or(format!("{}: Expected a list of rule ids", key))?;
+		rules.extend(rule::select(id).ok_or(format!("{}: No such rule: {}", key, id))?);
+	}
+	Ok(rules)
+}
+
+// The exclude patterns of a directory's configuration file, if any and valid.
+// Otherwise, the failure comes up when the settings are.
+pub fn read_excludes(dir: &Path) -> Vec<Pattern> {
+	match std::fs::read(dir.join(CONFIGFILE)) {
+		Ok(text) => parse(&text).map(|(_, exclude)| exclude).unwrap_or_default(),
+		Err(_) => Vec::new(),
+	}
+}
+
+pub struct Configs {
+	cwd: PathBuf,
+	// By directory: Where the nearest configuration file is.
+	nearest: HashMap<PathBuf, Option<PathBuf>>,
+	loaded: HashMap<PathBuf, Result<Config, String>>,
+}
+
+impl Configs {
+	pub fn new() -> Configs {
+		Configs {
+			cwd: std::env::current_dir().unwrap_or_default(),
+			nearest: HashMap::new(),
+			loaded: HashMap::new(),
+		}
+	}
+
+	// The configuration file that applies in the current directory, if any.
+	pub fn current(&mut self) -> Option<PathBuf> {
+		let cwd = self.cwd.clone();
+		self.find(&cwd).map(|dir| dir.join(CONFIGFILE))
+	}
+
+	// The settings for a file, or for the empty path (stdin or -c) in the current directory.
+	pub fn settings_for(&mut self, path: &Path, sett: &Settings, explicit: &Explicit) -> Result<Settings, String> {
+		let abs = normalize(&self.cwd.join(path));
+		let dir = if path.as_os_str().is_empty() {
+			abs.clone()
+		} else {
+			abs.parent().unwrap_or(&abs).to_owned()
+		};
+		let confdir = match self.find(&dir) {
+			Some(confdir) => confdir,
+			None => return Ok(sett.clone()),
+		};
+		let config = match &self.loaded[&confdir] {
+			Ok(config) => config,
+			Err(e) => return Err(format!("{}: {}", confdir.join(CONFIGFILE).display(), e)),
+		};
+		let mut sett = sett.clone();
+		config.options.apply(&mut sett, explicit);
+		if !path.as_os_str().is_empty() {
+			let rel = abs.strip_prefix(&confdir).unwrap().to_string_lossy();
+			for (patterns, options) in &config.overrides {
+				if patterns.iter().any(|p| p.matches_within(rel.as_bytes(), false)) {
+					options.apply(&mut sett, explicit);
+				}
+			}
+		}
+		Ok(sett)
+	}
+
+	fn find(&mut self, dir: &Path) -> Option<PathBuf> {
+		if let Some(nearest) = self.nearest.get(dir) {
+			return nearest.clone();
+		}
+		let nearest = if dir.join(CONFIGFILE).is_file() {
+			let config = match std::fs::read(dir.join(CONFIGFILE)) {
+				Ok(text) => parse(&text).map(|(config, _)| config),
+				Err(e) => Err(e.to_string()),
+			};
+			self.loaded.insert(dir.to_owned(), config);
+			Some(dir.to_owned())
+		} else {
+			dir.parent().and_then(|parent| self.find(parent))
+		};
+		self.nearest.insert(dir.to_owned(), nearest.clone());
+		nearest
+	}
+}
+
+impl Default for Configs {
+	fn default() -> Configs {
+		Configs::new()
+	}
+}
+
+#[test]
+fn test_parse() {
+	let (config, exclude) = parse(b"\
+		mode = \"check\"\n\
+		exclude = [\"vendor/\", \"*.gen.sh\"]\n\
+		[[override]]\n\
+		path = \"legacy/\"\n\
+		mode = \"suggest\"\n\
+		unified = 1\n\
+	").ok().unwrap();
+	assert_eq!(exclude.len(), 2);
+	assert_eq!(config.overrides.len(), 1);
+
+	let default = Settings::default();
+	let mut sett = Settings::default();
+	config.options.apply(&mut sett, &Explicit::default());
+	assert!(sett.osel == crate::machine::OutputSelector::Check);
+	let (patterns, options) = &config.overrides[0];
+	assert!(patterns[0].matches_within(b"legacy/a/x.sh", false));
+	assert!(!patterns[0].matches_within(b"ci/x.sh", false));
+	options.apply(&mut sett, &Explicit{mode: true, ..Explicit::default()});
+	assert!(sett.osel == crate::machine::OutputSelector::Check);
+	assert_eq!((sett.patch_context, default.patch_context), (1, 3));
+
+	let (config, _) = parse(b"disable = [\"all\"]\nenable = [\"quote-var\", \"pwd\"]\n").ok().unwrap();
+	let mut sett = Settings::default();
+	config.options.apply(&mut sett, &Explicit{rules: vec![Rule::Backtick], ..Explicit::default()});
+	assert!(sett.is_enabled(Rule::Quote));
+	assert!(sett.is_enabled(Rule::Backtick));
+	assert!(!sett.is_enabled(Rule::TestXyes));
+
+	let (config, _) = parse(b"dialect = \"sh\"\n[[override]]\npath = \"*.bash\"\ndialect = \"bash\"\n").ok().unwrap();
+	let mut sett = Settings::default();
+	config.options.apply(&mut sett, &Explicit::default());
+	assert!(!sett.is_enabled(Rule::ForInArray));
+	assert!(sett.is_enabled(Rule::Quote));
+	config.overrides[0].1.apply(&mut sett, &Explicit::default());
+	assert!(sett.is_enabled(Rule::ForInArray));
+
+	let (config, _) = parse(b"color = \"never\"\n[[override]]\npath = \"ci/\"\ncolor = \"auto\"\n").ok().unwrap();
+	let mut sett = Settings::default();
+	assert_eq!(sett.color, Some(true));
+	config.options.apply(&mut sett, &Explicit::default());
+	assert_eq!(sett.color, Some(false));
+	config.overrides[0].1.apply(&mut sett, &Explicit::default());
+	assert_eq!(sett.color, None);
+	let mut sett = Settings::default();
+	config.options.apply(&mut sett, &Explicit{color: true, ..Explicit::default()});
+	assert_eq!(sett.color, Some(true));
+
+	assert_eq!(parse(b"mode = \"loud\"\n").err().unwrap(), "mode: No such mode: loud");
+	assert_eq!(parse(b"color = \"rainbow\"\n").err().unwrap(), "color: Not auto, always or never: rainbow");
+	assert_eq!(parse(b"mode = \"replace\"\n").err().unwrap(), "mode: Replacing files takes the option --replace");
+	assert_eq!(parse(b"dialect = \"zsh\"\n").err().unwrap(), "dialect: Only bash and sh are supported, not zsh");
+	assert_eq!(parse(b"frobnicate = 1\n").err().unwrap(), "No such key: frobnicate");
+	assert_eq!(parse(b"disable = [\"xyes\"]\n").err().unwrap(), "disable: No such rule: xyes");
+	assert_eq!(parse(b"[[override]]\nmode = \"check\"\n").err().unwrap(), "override: Missing path");
+}
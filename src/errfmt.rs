@@ -8,14 +8,31 @@
 
 use std::io::Write;
 
+#[derive(Clone)]
+#[derive(Debug)]
 pub struct ContextualError{
+	pub id: &'static str,
 	pub typ: &'static str,
 	pub ctx: Vec<u8>,
 	pub pos: usize,
 	pub len: usize,
+	pub offset: usize, // of pos, from the start of the file
+	pub line: usize, // 1-based, of pos
+	pub col: usize, // 1-based, in codepoints
 	pub msg: &'static str,
 }
 
+// Lines shown before the failing line.
+pub const CONTEXT_LINES: usize = 2;
+
+pub fn codepoints(text: &[u8]) -> usize {
+	text.iter().filter(|&&c| c >> 6 != 0b10).count()
+}
+
+fn line_begin(text: &[u8], pos: usize) -> usize {
+	text[.. pos].iter().rposition(|&c| c == b'\n').map_or(0, |i| i + 1)
+}
+
 fn stderr_write_or_panic(lock: &mut std::io::StderrLock, bytes: &[u8]) {
 	if let Err(e) = lock.write_all(bytes) {
 		panic!("Unable to write to stderr: {}", e);
@@ -32,43 +49,189 @@ pub fn blame_path_io(path: &std::ffi::OsString, e: &std::io::Error) {
 	eprintln!("{}: {}", printable, e);
 }
 
-pub fn blame_syntax(path: &std::ffi::OsString, fail: &ContextualError) {
-	blame_path(path, fail.typ);
-	if fail.pos < fail.ctx.len() {
-		let mut i = fail.pos;
-		while i > 0 {
-			i -= 1;
-			if fail.ctx[i] == b'\n' {
-				break;
+pub fn blame_syntax(path: &std::ffi::OsString, fails: &[ContextualError]) {
+	for (i, fail) in fails.iter().enumerate() {
+		blame_location(path, fail);
+		// Each kind of error is explained once.
+		if fails[.. i].iter().all(|earlier| earlier.id != fail.id) {
+			eprintln!("{}", fail.msg);
+		}
+	}
+}
+
+fn blame_location(path: &std::ffi::OsString, fail: &ContextualError) {
+	let printable = path.to_string_lossy();
+	eprintln!("{}:{}:{}: {}", printable, fail.line, fail.col, fail.typ);
+	// At the end of file, the caret goes after the end.
+	if fail.pos <= fail.ctx.len() {
+		let failing_line_begin = line_begin(&fail.ctx, fail.pos);
+		let mut context_begin = failing_line_begin;
+		for _ in 0 .. CONTEXT_LINES {
+			if context_begin > 0 {
+				context_begin = line_begin(&fail.ctx, context_begin - 1);
 			}
 		}
-		let failing_line_begin = if fail.ctx[i] == b'\n' { i + 1 } else { 0 };
 		let mut i = fail.pos;
 		while i < fail.ctx.len() && fail.ctx[i] != b'\n' {
 			i += 1;
 		}
-		let failing_line = &fail.ctx[failing_line_begin .. i];
-
-		// FIXME: This counts codepoints, not displayed width.
-		let mut width = 0;
-		for c in &fail.ctx[failing_line_begin .. fail.pos] {
-			if c >> b'\x06' != b'\x02' {
-				width += 1;
-			}
-		}
-		let width = width;
+		let failing_lines = &fail.ctx[context_begin .. i];
+		let marked = match &fail.ctx[fail.pos .. i.min(fail.pos + fail.len)] {
+			b"" => b" ",
+			marked => marked,
+		};
+		let carets = caret_line(&fail.ctx[failing_line_begin .. fail.pos], marked);
 
 		let stderr = std::io::stderr();
 		let mut stderr_lock = stderr.lock();
-		stderr_write_or_panic(&mut stderr_lock, failing_line);
+		stderr_write_or_panic(&mut stderr_lock, failing_lines);
 		stderr_write_or_panic(&mut stderr_lock, b"\n");
-		for _ in 0 .. width {
-			stderr_write_or_panic(&mut stderr_lock, b" ");
-		}
-		for _ in 0 .. fail.len {
-			stderr_write_or_panic(&mut stderr_lock, b"^");
-		}
+		stderr_write_or_panic(&mut stderr_lock, &carets);
 		stderr_write_or_panic(&mut stderr_lock, b"\n");
 	}
-	eprintln!("{}", fail.msg);
+}
+
+// Carets under the marked text, after what comes before it on the line,
+// as it would be displayed in a terminal.
+// Tabs are copied, so that they go to the same tab stops.
+pub(crate) fn caret_line(before: &[u8], marked: &[u8]) -> Vec<u8> {
+	let mut carets = Vec::new();
+	for c in String::from_utf8_lossy(before).chars() {
+		if c == '\t' {
+			carets.push(b'\t');
+		} else {
+			carets.resize(carets.len() + display_width(c), b' ');
+		}
+	}
+	for c in String::from_utf8_lossy(marked).chars() {
+		let width = if c == '\t' { 1 } else { display_width(c) };
+		carets.resize(carets.len() + width, b'^');
+	}
+	carets
+}
+
+// Combining and other invisible characters take no columns, East Asian wide characters two.
+fn display_width(c: char) -> usize {
+	let c = c as u32;
+	let within = |ranges: &[(u32, u32)]| ranges.iter().any(|&(first, last)| first <= c && c <= last);
+	if c < 0x20 || (0x7f .. 0xa0).contains(&c) || within(ZERO_WIDTH) {
+		0
+	} else if within(WIDE) {
+		2
+	} else {
+		1
+	}
+}
+
+const ZERO_WIDTH: &[(u32, u32)] = &[
+	(0x0300, 0x036f), // Combining Diacritical Marks
+	(0x0483, 0x0489),
+	(0x0591, 0x05bd),
+	(0x05bf, 0x05bf),
+	(0x05c1, 0x05c2),
+	(0x05c4, 0x05c5),
+	(0x05c7, 0x05c7),
+	(0x0610, 0x061a),
+	(0x064b, 0x065f),
+	(0x0670, 0x0670),
+	(0x06d6, 0x06dc),
+	(0x06df, 0x06e4),
+	(0x06e7, 0x06e8),
+	(0x06ea, 0x06ed),
+	(0x0900, 0x0902),
+	(0x093a, 0x093a),
+	(0x093c, 0x093c),
+	(0x0941, 0x0948),
+	(0x094d, 0x094d),
+	(0x0e31, 0x0e31),
+	(0x0e34, 0x0e3a),
+	(0x0e47, 0x0e4e),
+	(0x1160, 0x11ff), // Hangul medial vowels and final consonants
+	(0x1ab0, 0x1aff),
+	(0x1dc0, 0x1dff),
+	(0x200b, 0x200f), // Zero width space, joiners and direction marks
+	(0x202a, 0x202e),
+	(0x2060, 0x2064),
+	(0x20d0, 0x20ff), // Combining Diacritical Marks for Symbols
+	(0x302a, 0x302d),
+	(0x3099, 0x309a),
+	(0xfe00, 0xfe0f), // Variation Selectors
+	(0xfe20, 0xfe2f),
+	(0xfeff, 0xfeff),
+	(0xe0100, 0xe01ef),
+];
+
+const WIDE: &[(u32, u32)] = &[
+	(0x1100, 0x115f), // Hangul initial consonants
+	(0x231a, 0x231b),
+	(0x2329, 0x232a),
+	(0x23e9, 0x23ec),
+	(0x23f0, 0x23f0),
+	(0x23f3, 0x23f3),
+	(0x25fd, 0x25fe),
+	(0x2614, 0x2615),
+	(0x2648, 0x2653),
+	(0x267f, 0x267f),
+	(0x2693, 0x2693),
+	(0x26a1, 0x26a1),
+	(0x26aa, 0x26ab),
+	(0x26bd, 0x26be),
+	(0x26c4, 0x26c5),
+	(0x26ce, 0x26ce),
+	(0x26d4, 0x26d4),
+	(0x26ea, 0x26ea),
+	(0x26f2, 0x26f3),
+	(0x26f5, 0x26f5),
+	(0x26fa, 0x26fa),
+	(0x26fd, 0x26fd),
+	(0x2705, 0x2705),
+	(0x270a, 0x270b),
+	(0x2728, 0x2728),
+	(0x274c, 0x274c),
+	(0x274e, 0x274e),
+	(0x2753, 0x2755),
+	(0x2757, 0x2757),
+	(0x2795, 0x2797),
+	(0x27b0, 0x27b0),
+	(0x27bf, 0x27bf),
+	(0x2b1b, 0x2b1c),
+	(0x2b50, 0x2b50),
+	(0x2b55, 0x2b55),
+	(0x2e80, 0x3029), // CJK radicals, punctuation and symbols
+	(0x302e, 0x303e),
+	(0x3041, 0x3098), // Hiragana
+	(0x309b, 0x33ff), // Katakana, and more CJK
+	(0x3400, 0x4dbf), // CJK Unified Ideographs Extension A
+	(0x4e00, 0x9fff), // CJK Unified Ideographs
+	(0xa000, 0xa4cf), // Yi
+	(0xa960, 0xa97f),
+	(0xac00, 0xd7a3), // Hangul Syllables
+	(0xf900, 0xfaff), // CJK Compatibility Ideographs
+	(0xfe10, 0xfe19),
+	(0xfe30, 0xfe6f),
+	(0xff00, 0xff60), // Fullwidth Forms
+	(0xffe0, 0xffe6),
+	(0x16fe0, 0x16fe4),
+	(0x17000, 0x18cff), // Tangut
+	(0x1b000, 0x1b2ff), // Kana Supplement
+	(0x1f004, 0x1f004),
+	(0x1f0cf, 0x1f0cf),
+	(0x1f18e, 0x1f18e),
+	(0x1f191, 0x1f19a),
+	(0x1f200, 0x1f251),
+	(0x1f300, 0x1f64f), // Emoji
+	(0x1f680, 0x1f6ff),
+	(0x1f7e0, 0x1f7eb),
+	(0x1f90c, 0x1f9ff),
+	(0x1fa70, 0x1faff),
+	(0x20000, 0x2fffd), // CJK Unified Ideographs Extension B and beyond
+	(0x30000, 0x3fffd),
+];
+
+#[test]
+fn test_caret_line() {
+	assert_eq!(caret_line(b"echo ", b"$10"), b"     ^^^");
+	assert_eq!(caret_line(b"\techo \t", b"$10"), b"\t     \t^^^");
+	assert_eq!(caret_line("echo 日本 ".as_bytes(), b"$10"), b"          ^^^");
+	assert_eq!(caret_line("echo e\u{301} ".as_bytes(), "$1日".as_bytes()), b"       ^^^^");
 }
@@ -0,0 +1,275 @@
+/*
+ * Copyright 2024 Andreas Nordal
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+// The long story behind each rule and syntax error, as told by --explain.
+// Each has the rationale, examples of before and after,
+// and the cases where the rewrite makes the script do something else.
+
+use crate::rule;
+use crate::rule::Rule;
+
+// By rule id or syntax error id.
+pub fn explain(id: &str) -> Option<String> {
+	let (summary, text) = match rule::select(id) {
+		Some(rules) if rules.len() == 1 => (rules[0].summary(), rule_text(rules[0])),
+		_ => {
+			let &(_, summary) = rule::SYNTAX_ERRORS.iter().find(|(error, _)| *error == id)?;
+			(summary, syntax_error_text(id)?)
+		}
+	};
+	Some(format!("{}: {}\n\n{}", id, summary, text))
+}
+
+// One line for each rule, then each syntax error.
+pub fn list() -> String {
+	let mut out = String::new();
+	for rule in rule::ALL {
+		let note = if !rule.is_default() {
+			" (not enabled by default)"
+		} else if rule.changes_behavior() {
+			" (changes behavior)"
+		} else {
+			""
+		};
+		out.push_str(&format!("{:<18} {}{}\n", rule.id(), rule.summary(), note));
+	}
+	for (id, summary) in rule::SYNTAX_ERRORS {
+		out.push_str(&format!("{:<18} {} (syntax error)\n", id, summary));
+	}
+	out
+}
+
+fn rule_text(rule: Rule) -> &'static str {
+	match rule {
+		Rule::Quote => "\
+An unquoted variable or command substitution is split into words on
+whitespace, and each word that contains wildcards is replaced by the
+filenames it matches. Most of the time, this is invisible, because the value
+is one word without wildcards. Then, a filename with a space in it comes
+along, and the script operates on the wrong files.
+
+Quoting inhibits word splitting and indirect pathname expansion.
+Where quoting is unnecessary, like in assignments, after case and inside
+[[ ]], or for variables of numeric content like $? and $#, it is left as is.
+
+Before:
+	cp $src $dst
+	echo $(date)
+After:
+	cp \"$src\" \"$dst\"
+	echo \"$(date)\"
+
+Changes behavior: If the script relied on splitting a string into words,
+or on wildcards in a variable, it won't do that anymore. Use an array for
+lists of words, and write out the wildcards.",
+
+		Rule::DollarStar => "\
+$* and $@ both expand to the arguments, but only \"$@\" passes them on as
+they were: $* and $@ unquoted split each argument into words, and \"$*\"
+joins them into one.
+
+Before:
+	exec some_command $*
+After:
+	exec some_command \"$@\"
+
+Changes behavior: If the arguments were meant to be joined or split.",
+
+		Rule::Backtick => "\
+Backticks are the old form of command substitution. They need escaping to
+nest, and the escaping rules differ between quoted and unquoted backticks,
+which is easy to get wrong. The $( ) form nests without escaping.
+Backticks are left alone where quotes are unnecessary, like in assignments.
+
+Before:
+	echo `date`
+After:
+	echo \"$(date)\"
+
+Changes behavior: Only if the backticks contained escapes that mean
+something else inside $( ).",
+
+		Rule::Pwd => "\
+The shell keeps the current directory in $PWD, so running pwd is a waste,
+and its output would need quoting like any other command substitution.
+
+Before:
+	cd $(pwd)/build
+After:
+	cd \"$PWD\"/build
+
+Changes behavior: Only if pwd is something other than the builtin,
+or if $PWD is modified by the script.",
+
+		Rule::VarBrace => "\
+Braces around a variable name are needed to delimit the name from
+characters that could continue it. Elsewhere, they are noise, and they
+tend to stand in for the quotes that were needed. When the variable is
+quoted by itself, they are removed. In string interpolations, they are left
+alone. To keep them, --keep-varbraces or --disable varbrace.
+
+Before:
+	echo \"${name}\"
+	cp ${src} ${dst}
+After:
+	echo \"$name\"
+	cp \"$src\" \"$dst\"
+
+Changes behavior: Never.",
+
+		Rule::ArrayStar => "\
+${array[*]} joins the elements of the array into one string, or splits
+them into words if unquoted, whereas \"${array[@]}\" expands to each element
+as it is.
+
+Before:
+	rm -- ${files[*]}
+After:
+	rm -- \"${files[@]}\"
+
+Changes behavior: If the elements were meant to be joined or split.",
+
+		Rule::ForInArray => "\
+A loop over one unquoted variable loops over its words, which means the
+variable is used as a list. The way to keep a list in bash is an array,
+so this loops over the array instead.
+
+Before:
+	for f in $files; do
+After:
+	for f in \"${files[@]}\"; do
+
+Changes behavior: If the variable is a string and not an array, the loop
+runs once, for the whole string. Then, the variable must be made an array
+where it is assigned: files=(a b c) instead of files=\"a b c\".",
+
+		Rule::TestEmptiness => "\
+The -n and -z flags of test are string comparisons with the empty string
+in disguise. For readability, they are written as such comparisons.
+
+Before:
+	[ -z \"$s\" ]
+	[ -n \"$s\" ]
+After:
+	[ \"$s\" = \"\" ]
+	[ \"$s\" != \"\" ]
+
+Changes behavior: Never.",
+
+		Rule::TestXyes => "\
+Prefixing both sides of a comparison with x was a workaround for old shells
+that mistook empty strings and strings that look like operators. With
+quoting, and with the number of arguments deciding the meaning of a test,
+it is unnecessary. It is removed where the x is unquoted.
+
+Before:
+	[ x$answer = xyes ]
+After:
+	[ \"$answer\" = yes ]
+
+Changes behavior: Never, unless the test is combined with -a or -o,
+which is ambiguous anyway. Use && and || between tests instead.",
+
+		Rule::PrematureEsac => "\
+The last arm of a case statement may end without ;;, but then, adding
+another arm after it requires remembering to terminate it. The ;; is added.
+
+Before:
+	case $x in
+	a) echo a;;
+	b) echo b
+	esac
+After:
+	case $x in
+	a) echo a;;
+	b) echo b
+	;; esac
+
+Changes behavior: Never.",
+
+		Rule::PositionalBraces => "\
+Only the first digit after $ is part of the name of a positional parameter:
+$10 is ${1} followed by 0. This is a syntax error by default, because fixing
+it would change what the script does, however obviously wrong it was.
+With --unbreak, braces are put around all the digits, as was likely meant.
+
+Before:
+	echo \"$10\"
+After, with --unbreak:
+	echo \"${10}\"
+
+Changes behavior: Always. The script will expand the 10th argument instead
+of the 1st followed by 0. Review what was meant.",
+
+		Rule::TestNUnquoted => "\
+test -n with an unquoted operand is always true when the operand is empty,
+because the test then gets only one argument, -n, which is a non-empty
+string. Quoting the operand makes it check the variable as intended.
+
+Before:
+	[ -n $s ]
+After:
+	[ \"$s\" != \"\" ]
+
+Changes behavior: Always, where the variable is empty or unset.
+The test was broken, but code may have come to depend on it being true.
+That is why this rule takes --unbreak. Otherwise, the same quotes are
+suggested as ordinary quoting, by the quote-var rule.",
+
+		Rule::AddVarBraces => "\
+Some style guides want braces around every variable name. Shellharden
+doesn't, but with --enable add-varbraces, it adds them. This turns off the
+varbrace rule, which would otherwise remove them again.
+
+Before:
+	echo \"$name\" $other
+After:
+	echo \"${name}\" \"${other}\"
+
+Changes behavior: Never.",
+	}
+}
+
+fn syntax_error_text(id: &str) -> Option<&'static str> {
+	Some(match id {
+		"doubledigit" => "\
+$10 looks like the 10th positional parameter, but only the first digit is
+part of the name: It is ${1} followed by 0. This is left as it is, because
+fixing what it does would slip through review unnoticed, and fixing how it
+looks would make a likely bug look intentional. Fix it by hand, with braces
+where they belong: ${10} or ${1}0. Or, with --unbreak, by the
+positional-braces rule, which assumes ${10} was meant.
+
+Before:
+	echo \"$10\"
+After, by hand:
+	echo \"${10}\"",
+
+		"unexpected-eof" => "\
+The file ended while something was still open: A quote, a parenthesis,
+a here-document or the like. Either the file is truncated, or the parser
+got lost on syntax it doesn't understand. Check that the script runs with
+bash -n, and if it does, please report the construct that confused
+Shellharden.",
+
+		_ => return None,
+	})
+}
+
+#[test]
+fn test_explain() {
+	for rule in rule::ALL {
+		assert!(explain(rule.id()).unwrap().starts_with(rule.id()));
+	}
+	for (id, _) in rule::SYNTAX_ERRORS {
+		assert!(explain(id).is_some());
+	}
+	assert!(explain("all").is_none());
+	assert!(explain("nope").is_none());
+	assert_eq!(list().lines().count(), rule::ALL.len() + rule::SYNTAX_ERRORS.len());
+}
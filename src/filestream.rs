@@ -9,9 +9,14 @@
 use std::io::{Read, Seek, Write};
 use std::fmt::{Write as FmtWrite};
 
+use crate::annotate::Notes;
+use crate::errfmt::ContextualError;
+use crate::machine::Edit;
+
 pub enum InputSource<'a> {
 	File(std::fs::File),
 	Stdin(std::io::StdinLock<'a>),
+	Bytes(&'a [u8]),
 }
 
 impl<'a> InputSource<'a> {
@@ -21,15 +26,20 @@ impl<'a> InputSource<'a> {
 	pub fn open_stdin(stdin: &std::io::Stdin) -> InputSource<'a> {
 		InputSource::Stdin(stdin.lock())
 	}
+	pub fn open_bytes(bytes: &'a [u8]) -> InputSource<'a> {
+		InputSource::Bytes(bytes)
+	}
 	pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, std::io::Error> {
 		match *self {
 			InputSource::Stdin(ref mut fh) => fh.read(buf),
 			InputSource::File (ref mut fh) => fh.read(buf),
+			InputSource::Bytes(ref mut bytes) => bytes.read(buf),
 		}
 	}
 	pub fn size(&mut self) -> Result<u64, std::io::Error> {
 		match *self {
 			InputSource::Stdin(_) => panic!("filesize of stdin"),
+			InputSource::Bytes(bytes) => Ok(bytes.len() as u64),
 			InputSource::File (ref mut fh) => {
 				let off :u64 = fh.seek(std::io::SeekFrom::End(0))?;
 				fh.seek(std::io::SeekFrom::Start(0))?;
@@ -39,6 +49,27 @@ impl<'a> InputSource<'a> {
 	}
 }
 
+pub fn slurp(path: &std::ffi::OsString) -> Result<Vec<u8>, std::io::Error> {
+	let mut content = Vec::new();
+	if path.is_empty() {
+		std::io::stdin().lock().read_to_end(&mut content)?;
+	} else {
+		std::fs::File::open(path)?.read_to_end(&mut content)?;
+	}
+	Ok(content)
+}
+
+// As given by the OS, for arguments that are not names.
+#[cfg(unix)]
+pub fn os_bytes(s: &std::ffi::OsStr) -> std::borrow::Cow<'_, [u8]> {
+	std::borrow::Cow::Borrowed(std::os::unix::ffi::OsStrExt::as_bytes(s))
+}
+
+#[cfg(not(unix))]
+pub fn os_bytes(s: &std::ffi::OsStr) -> std::borrow::Cow<'_, [u8]> {
+	std::borrow::Cow::Owned(s.to_string_lossy().into_owned().into_bytes())
+}
+
 pub enum OutputSink<'a> {
 	Stdout(std::io::StdoutLock<'a>),
 	Soak(Vec<u8>),
@@ -48,17 +79,21 @@ pub enum OutputSink<'a> {
 pub struct FileOut<'a> {
 	pub sink :OutputSink<'a>,
 	pub change :bool,
+	pub edits :Vec<Edit>,
+	// Of the changes on the current line, if they are to be annotated.
+	pub notes :Option<Notes>,
+	pub warnings :Vec<ContextualError>,
 }
 
 impl<'a> FileOut<'a> {
 	pub fn open_stdout(stdout: &std::io::Stdout) -> FileOut<'a> {
-		FileOut{sink: OutputSink::Stdout(stdout.lock()), change: false}
+		FileOut{sink: OutputSink::Stdout(stdout.lock()), change: false, edits: Vec::new(), notes: None, warnings: Vec::new()}
 	}
 	pub fn open_soak(reserve: u64) -> FileOut<'a> {
-		FileOut{sink: OutputSink::Soak(Vec::with_capacity(reserve as usize)), change: false}
+		FileOut{sink: OutputSink::Soak(Vec::with_capacity(reserve as usize)), change: false, edits: Vec::new(), notes: None, warnings: Vec::new()}
 	}
 	pub fn open_none() -> FileOut<'a> {
-		FileOut{sink: OutputSink::None, change: false}
+		FileOut{sink: OutputSink::None, change: false, edits: Vec::new(), notes: None, warnings: Vec::new()}
 	}
 	pub fn write_all(&mut self, buf: &[u8]) -> Result<(), std::io::Error> {
 		match self.sink {
@@ -83,16 +118,10 @@ impl<'a> FileOut<'a> {
 		}
 		Ok(())
 	}
-	pub fn commit(&mut self, path: &std::ffi::OsString) -> Result<(), std::io::Error> {
+	pub fn commit(&mut self, path: &std::ffi::OsString, backup: Option<&str>) -> Result<(), std::io::Error> {
 		if self.change {
 			if let OutputSink::Soak(ref vec) = self.sink {
-				std::fs::OpenOptions::new()
-					.write(true)
-					.truncate(true)
-					.create(false)
-					.open(path)?
-					.write_all(vec)?
-				;
+				crate::replace::replace(std::path::Path::new(path), vec, backup)?;
 			}
 		}
 		Ok(())
@@ -0,0 +1,289 @@
+/*
+ * Copyright 2024 Andreas Nordal
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+// Which files to skip: --exclude, --include, .shellhardenignore files
+// and the exclude list of .shellharden.toml files, all in gitignore syntax.
+
+use std::collections::HashMap;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+pub const IGNOREFILE: &str = ".shellhardenignore";
+
+pub struct Pattern {
+	glob: Vec<u8>,
+	negated: bool,
+	dir_only: bool,
+	// Whether to match the whole relative path, as opposed to just the name.
+	anchored: bool,
+}
+
+impl Pattern {
+	// None for blank lines and comments.
+	pub fn parse(line: &[u8]) -> Option<Pattern> {
+		let mut glob = line;
+		while let Some((&last, init)) = glob.split_last() {
+			let escaped = init.last() == Some(&b'\\');
+			if last == b'\r' || (last == b' ' && !escaped) {
+				glob = init;
+			} else {
+				break;
+			}
+		}
+		if glob.is_empty() || glob[0] == b'#' {
+			return None;
+		}
+		let negated = glob[0] == b'!';
+		if negated {
+			glob = &glob[1 ..];
+		}
+		let dir_only = glob.last() == Some(&b'/');
+		if dir_only {
+			glob = &glob[.. glob.len() - 1];
+		}
+		let anchored = glob.contains(&b'/');
+		if glob.first() == Some(&b'/') {
+			glob = &glob[1 ..];
+		}
+		if glob.is_empty() {
+			return None;
+		}
+		Some(Pattern{glob: glob.to_owned(), negated, dir_only, anchored})
+	}
+
+	pub fn matches(&self, rel: &[u8], is_dir: bool) -> bool {
+		if self.dir_only && !is_dir {
+			return false;
+		}
+		if self.anchored {
+			glob_match(&self.glob, rel)
+		} else {
+			let name = match rel.iter().rposition(|&c| c == b'/') {
+				Some(slash) => &rel[slash + 1 ..],
+				None => rel,
+			};
+			glob_match(&self.glob, name)
+		}
+	}
+
+	// Whether it matches the path or a directory it is in.
+	pub fn matches_within(&self, rel: &[u8], is_dir: bool) -> bool {
+		rel.iter().enumerate().any(|(i, &c)| c == b'/' && self.matches(&rel[.. i], true))
+			|| self.matches(rel, is_dir)
+	}
+}
+
+pub struct Filter {
+	excludes: Vec<Pattern>,
+	includes: Vec<Pattern>,
+	cwd: PathBuf,
+	// The patterns of each directory's ignore file, if read.
+	ignorefiles: HashMap<PathBuf, Vec<Pattern>>,
+}
+
+impl Filter {
+	pub fn new() -> Filter {
+		Filter {
+			excludes: Vec::new(),
+			includes: Vec::new(),
+			cwd: std::env::current_dir().unwrap_or_default(),
+			ignorefiles: HashMap::new(),
+		}
+	}
+	pub fn exclude(&mut self, glob: &str) {
+		self.excludes.extend(Pattern::parse(glob.as_bytes()));
+	}
+	pub fn include(&mut self, glob: &str) {
+		self.includes.extend(Pattern::parse(glob.as_bytes()));
+	}
+	pub fn has_includes(&self) -> bool {
+		!self.includes.is_empty()
+	}
+
+	pub fn is_included(&self, path: &Path) -> bool {
+		let given = given_path(path);
+		self.includes.is_empty() || self.includes.iter().any(|p| p.matches(&given, false))
+	}
+
+	// Whether --exclude or an ignore file applies to the path or a directory it is in.
+	pub fn is_excluded(&mut self, path: &Path, is_dir: bool) -> bool {
+		let given = given_path(path);
+		self.excludes.iter().any(|p| p.matches_within(&given, is_dir)) || self.is_ignored(path, is_dir)
+	}
+
+	fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+		let abs = normalize(&self.cwd.join(path));
+		let ancestors: Vec<&Path> = abs.ancestors().collect();
+		for dir in ancestors.iter().skip(1) {
+			self.load(dir);
+		}
+		// From the outermost directory inwards, so that negations can't undo a parent's exclusion.
+		for (depth, sub) in ancestors.iter().enumerate().rev().skip(1) {
+			let sub_is_dir = depth != 0 || is_dir;
+			let mut ignored = false;
+			for dir in ancestors[depth + 1 ..].iter().rev() {
+				let rel = sub.strip_prefix(dir).unwrap().to_string_lossy();
+				for pattern in &self.ignorefiles[*dir] {
+					if pattern.matches(rel.as_bytes(), sub_is_dir) {
+						ignored = !pattern.negated;
+					}
+				}
+			}
+			if ignored {
+				return true;
+			}
+		}
+		false
+	}
+
+	fn load(&mut self, dir: &Path) {
+		if self.ignorefiles.contains_key(dir) {
+			return;
+		}
+		let mut patterns = crate::config::read_excludes(dir);
+		if let Ok(content) = std::fs::read(dir.join(IGNOREFILE)) {
+			patterns.extend(content.split(|&c| c == b'\n').filter_map(Pattern::parse));
+		}
+		self.ignorefiles.insert(dir.to_owned(), patterns);
+	}
+}
+
+impl Default for Filter {
+	fn default() -> Filter {
+		Filter::new()
+	}
+}
+
+// The path as the user would recognize it, to match --exclude and --include against.
+fn given_path(path: &Path) -> Vec<u8> {
+	let path = path.to_string_lossy();
+	let mut path = path.as_bytes();
+	while path.starts_with(b"./") {
+		path = &path[2 ..];
+	}
+	path.to_owned()
+}
+
+// Without following symlinks, unlike canonicalize.
+pub fn normalize(path: &Path) -> PathBuf {
+	let mut normal = PathBuf::new();
+	for component in path.components() {
+		match component {
+			Component::CurDir => {}
+			Component::ParentDir => {
+				normal.pop();
+			}
+			_ => normal.push(component),
+		}
+	}
+	normal
+}
+
+// Like fnmatch with FNM_PATHNAME, plus ** for any number of directories.
+pub fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+	match pattern.first() {
+		None => text.is_empty(),
+		Some(b'*') => {
+			if pattern.starts_with(b"**/") {
+				let rest = &pattern[3 ..];
+				return glob_match(rest, text) || text.iter().enumerate().any(|(i, &c)| {
+					c == b'/' && glob_match(rest, &text[i + 1 ..])
+				});
+			}
+			if pattern == b"**" {
+				return true;
+			}
+			let rest = &pattern[1 ..];
+			for i in 0 ..= text.len() {
+				if glob_match(rest, &text[i ..]) {
+					return true;
+				}
+				if i < text.len() && text[i] == b'/' {
+					break;
+				}
+			}
+			false
+		}
+		Some(b'?') => match text.first() {
+			Some(&c) if c != b'/' => glob_match(&pattern[1 ..], &text[1 ..]),
+			_ => false,
+		},
+		Some(b'[') if !text.is_empty() => match class(pattern, text[0]) {
+			Some((len, found)) => found && text[0] != b'/' && glob_match(&pattern[len ..], &text[1 ..]),
+			None => text[0] == b'[' && glob_match(&pattern[1 ..], &text[1 ..]),
+		},
+		Some(b'\\') if pattern.len() > 1 => {
+			text.first() == Some(&pattern[1]) && glob_match(&pattern[2 ..], &text[1 ..])
+		}
+		Some(&c) => text.first() == Some(&c) && glob_match(&pattern[1 ..], &text[1 ..]),
+	}
+}
+
+// The length of the bracket expression at the start of the pattern,
+// and whether it matches the character. None if it is unterminated.
+fn class(pattern: &[u8], c: u8) -> Option<(usize, bool)> {
+	let mut i = 1;
+	let negated = matches!(pattern.get(i), Some(b'!') | Some(b'^'));
+	if negated {
+		i += 1;
+	}
+	let mut found = false;
+	let mut first = true;
+	loop {
+		let lo = *pattern.get(i)?;
+		if lo == b']' && !first {
+			break;
+		}
+		first = false;
+		match pattern.get(i + 1 .. i + 3) {
+			Some(&[b'-', hi]) if hi != b']' => {
+				found |= lo <= c && c <= hi;
+				i += 3;
+			}
+			_ => {
+				found |= lo == c;
+				i += 1;
+			}
+		}
+	}
+	Some((i + 1, found != negated))
+}
+
+#[test]
+fn test_glob_match() {
+	assert!(glob_match(b"*.sh", b"a.sh"));
+	assert!(!glob_match(b"*.sh", b"dir/a.sh"));
+	assert!(glob_match(b"dir/*.sh", b"dir/a.sh"));
+	assert!(glob_match(b"**/a.sh", b"a.sh"));
+	assert!(glob_match(b"**/a.sh", b"x/y/a.sh"));
+	assert!(glob_match(b"x/**", b"x/y/a.sh"));
+	assert!(glob_match(b"x/**/a.sh", b"x/a.sh"));
+	assert!(!glob_match(b"x/**", b"x"));
+	assert!(glob_match(b"?.[a-c]", b"x.b"));
+	assert!(!glob_match(b"?.[!a-c]", b"x.b"));
+	assert!(glob_match(b"[]x]", b"]"));
+	assert!(glob_match(b"\\*", b"*"));
+	assert!(!glob_match(b"\\*", b"a"));
+	assert!(glob_match(b"[x", b"[x"));
+}
+
+#[test]
+fn test_pattern() {
+	assert!(Pattern::parse(b"# comment").is_none());
+	assert!(Pattern::parse(b"  ").is_none());
+	let p = Pattern::parse(b"vendor/ ").unwrap();
+	assert!(p.matches(b"a/vendor", true));
+	assert!(!p.matches(b"a/vendor", false));
+	let p = Pattern::parse(b"/gen/*.sh\r").unwrap();
+	assert!(p.matches(b"gen/x.sh", false));
+	assert!(!p.matches(b"a/gen/x.sh", false));
+	let p = Pattern::parse(b"!keep.sh").unwrap();
+	assert!(p.negated);
+	assert!(p.matches(b"a/keep.sh", false));
+}
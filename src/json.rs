@@ -0,0 +1,330 @@
+/*
+ * Copyright 2024 Andreas Nordal
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::fmt::Write;
+
+use crate::report::Finding;
+
+// Invalid UTF-8 is replaced, as JSON can't represent it.
+pub fn write_str(out: &mut Vec<u8>, s: &[u8]) {
+	out.push(b'\"');
+	for c in String::from_utf8_lossy(s).chars() {
+		match c {
+			'\"' => out.extend_from_slice(b"\\\""),
+			'\\' => out.extend_from_slice(b"\\\\"),
+			'\n' => out.extend_from_slice(b"\\n"),
+			'\r' => out.extend_from_slice(b"\\r"),
+			'\t' => out.extend_from_slice(b"\\t"),
+			'\x00' ..= '\x1f' | '\x7f' => {
+				let mut esc = String::new();
+				let _ = write!(esc, "\\u{:04x}", c as u32);
+				out.extend_from_slice(esc.as_bytes());
+			}
+			_ => {
+				let mut utf8 = [0; 4];
+				out.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+			}
+		}
+	}
+	out.push(b'\"');
+}
+
+pub fn write_opt_str(out: &mut Vec<u8>, s: Option<&[u8]>) {
+	match s {
+		Some(s) => write_str(out, s),
+		None => out.extend_from_slice(b"null"),
+	}
+}
+
+pub fn write_uint(out: &mut Vec<u8>, n: usize) {
+	out.extend_from_slice(n.to_string().as_bytes());
+}
+
+// One object per line.
+pub fn write_finding(out: &mut Vec<u8>, path: &str, finding: &Finding) {
+	out.extend_from_slice(b"{\"file\":");
+	write_str(out, path.as_bytes());
+	out.extend_from_slice(b",\"kind\":");
+	write_str(out, if finding.replacement.is_some() { b"change" } else { b"error" });
+	out.extend_from_slice(b",\"line\":");
+	write_uint(out, finding.region.line);
+	out.extend_from_slice(b",\"column\":");
+	write_uint(out, finding.region.col);
+	out.extend_from_slice(b",\"offset\":");
+	write_uint(out, finding.region.pos);
+	out.extend_from_slice(b",\"length\":");
+	write_uint(out, finding.region.len);
+	out.extend_from_slice(b",\"original\":");
+	write_str(out, finding.original);
+	out.extend_from_slice(b",\"replacement\":");
+	write_opt_str(out, finding.replacement.as_deref());
+	out.extend_from_slice(b",\"edits\":[");
+	for (i, (region, replacement)) in finding.edits.iter().enumerate() {
+		if i != 0 {
+			out.push(b',');
+		}
+		out.extend_from_slice(b"{\"offset\":");
+		write_uint(out, region.pos);
+		out.extend_from_slice(b",\"length\":");
+		write_uint(out, region.len);
+		out.extend_from_slice(b",\"replacement\":");
+		write_str(out, replacement);
+		out.extend_from_slice(b"}");
+	}
+	out.extend_from_slice(b"]");
+	out.extend_from_slice(b",\"rule\":");
+	write_str(out, finding.rule.as_bytes());
+	out.extend_from_slice(b",\"message\":");
+	write_str(out, finding.message.as_bytes());
+	out.extend_from_slice(b"}\n");
+}
+
+pub enum Value {
+	Null,
+	Bool(bool),
+	Number(f64),
+	Str(String),
+	Array(Vec<Value>),
+	Object(Vec<(String, Value)>),
+}
+
+impl Value {
+	pub fn get(&self, key: &str) -> &Value {
+		if let Value::Object(members) = self {
+			for (k, v) in members {
+				if k == key {
+					return v;
+				}
+			}
+		}
+		&Value::Null
+	}
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			Value::Str(s) => Some(s),
+			_ => None,
+		}
+	}
+	pub fn as_bool(&self) -> Option<bool> {
+		match *self {
+			Value::Bool(b) => Some(b),
+			_ => None,
+		}
+	}
+	pub fn as_uint(&self) -> Option<usize> {
+		match *self {
+			Value::Number(n) if n >= 0.0 && n.fract() == 0.0 => Some(n as usize),
+			_ => None,
+		}
+	}
+	pub fn as_array(&self) -> &[Value] {
+		match self {
+			Value::Array(a) => a,
+			_ => &[],
+		}
+	}
+}
+
+// For echoing values back, such as request ids.
+pub fn write_value(out: &mut Vec<u8>, value: &Value) {
+	match value {
+		Value::Null => out.extend_from_slice(b"null"),
+		Value::Bool(b) => out.extend_from_slice(if *b { b"true" } else { b"false" }),
+		Value::Number(n) => out.extend_from_slice(n.to_string().as_bytes()),
+		Value::Str(s) => write_str(out, s.as_bytes()),
+		Value::Array(a) => {
+			out.push(b'[');
+			for (i, v) in a.iter().enumerate() {
+				if i != 0 {
+					out.push(b',');
+				}
+				write_value(out, v);
+			}
+			out.push(b']');
+		}
+		Value::Object(members) => {
+			out.push(b'{');
+			for (i, (k, v)) in members.iter().enumerate() {
+				if i != 0 {
+					out.push(b',');
+				}
+				write_str(out, k.as_bytes());
+				out.push(b':');
+				write_value(out, v);
+			}
+			out.push(b'}');
+		}
+	}
+}
+
+pub fn parse(text: &[u8]) -> Result<Value, &'static str> {
+	let mut parser = Parser{text, pos: 0};
+	let value = parser.value()?;
+	parser.skip_ws();
+	if parser.pos != text.len() {
+		return Err("Trailing garbage");
+	}
+	Ok(value)
+}
+
+struct Parser<'a> {
+	text: &'a [u8],
+	pos: usize,
+}
+
+impl<'a> Parser<'a> {
+	fn skip_ws(&mut self) {
+		while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.text.get(self.pos) {
+			self.pos += 1;
+		}
+	}
+	fn peek(&mut self) -> Option<u8> {
+		self.skip_ws();
+		self.text.get(self.pos).copied()
+	}
+	fn expect(&mut self, c: u8) -> Result<(), &'static str> {
+		if self.peek() != Some(c) {
+			return Err("Unexpected character");
+		}
+		self.pos += 1;
+		Ok(())
+	}
+	fn keyword(&mut self, word: &[u8], value: Value) -> Result<Value, &'static str> {
+		if !self.text[self.pos ..].starts_with(word) {
+			return Err("Unexpected character");
+		}
+		self.pos += word.len();
+		Ok(value)
+	}
+	fn value(&mut self) -> Result<Value, &'static str> {
+		match self.peek() {
+			Some(b'{') => {
+				self.pos += 1;
+				let mut members = Vec::new();
+				if self.peek() == Some(b'}') {
+					self.pos += 1;
+					return Ok(Value::Object(members));
+				}
+				loop {
+					if self.peek() != Some(b'"') {
+						return Err("Expected a string");
+					}
+					let key = self.string()?;
+					self.expect(b':')?;
+					members.push((key, self.value()?));
+					match self.peek() {
+						Some(b',') => self.pos += 1,
+						Some(b'}') => {
+							self.pos += 1;
+							return Ok(Value::Object(members));
+						}
+						_ => return Err("Unexpected character"),
+					}
+				}
+			}
+			Some(b'[') => {
+				self.pos += 1;
+				let mut elements = Vec::new();
+				if self.peek() == Some(b']') {
+					self.pos += 1;
+					return Ok(Value::Array(elements));
+				}
+				loop {
+					elements.push(self.value()?);
+					match self.peek() {
+						Some(b',') => self.pos += 1,
+						Some(b']') => {
+							self.pos += 1;
+							return Ok(Value::Array(elements));
+						}
+						_ => return Err("Unexpected character"),
+					}
+				}
+			}
+			Some(b'"') => Ok(Value::Str(self.string()?)),
+			Some(b't') => self.keyword(b"true", Value::Bool(true)),
+			Some(b'f') => self.keyword(b"false", Value::Bool(false)),
+			Some(b'n') => self.keyword(b"null", Value::Null),
+			Some(b'-' | b'0' ..= b'9') => {
+				let begin = self.pos;
+				while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0' ..= b'9') = self.text.get(self.pos) {
+					self.pos += 1;
+				}
+				let number = std::str::from_utf8(&self.text[begin .. self.pos]).unwrap();
+				number.parse::<f64>().map(Value::Number).map_err(|_| "Invalid number")
+			}
+			Some(_) => Err("Unexpected character"),
+			None => Err("Unexpected end of input"),
+		}
+	}
+	// Called at the opening quote.
+	fn string(&mut self) -> Result<String, &'static str> {
+		self.pos += 1;
+		let mut s = Vec::<u8>::new();
+		loop {
+			let c = *self.text.get(self.pos).ok_or("Unterminated string")?;
+			self.pos += 1;
+			match c {
+				b'"' => break,
+				b'\\' => {
+					let esc = *self.text.get(self.pos).ok_or("Unterminated string")?;
+					self.pos += 1;
+					match esc {
+						b'"' | b'\\' | b'/' => s.push(esc),
+						b'b' => s.push(8),
+						b'f' => s.push(12),
+						b'n' => s.push(b'\n'),
+						b'r' => s.push(b'\r'),
+						b't' => s.push(b'\t'),
+						b'u' => {
+							let mut code = self.hex4()?;
+							if (0xd800 .. 0xdc00).contains(&code) && self.text[self.pos ..].starts_with(b"\\u") {
+								self.pos += 2;
+								let low = self.hex4()?;
+								code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+							}
+							let c = char::from_u32(code).unwrap_or('\u{fffd}');
+							let mut utf8 = [0; 4];
+							s.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+						}
+						_ => return Err("Invalid escape"),
+					}
+				}
+				_ => s.push(c),
+			}
+		}
+		String::from_utf8(s).map_err(|_| "Invalid UTF-8")
+	}
+	fn hex4(&mut self) -> Result<u32, &'static str> {
+		let digits = self.text.get(self.pos .. self.pos + 4).ok_or("Invalid escape")?;
+		let digits = std::str::from_utf8(digits).map_err(|_| "Invalid escape")?;
+		self.pos += 4;
+		u32::from_str_radix(digits, 16).map_err(|_| "Invalid escape")
+	}
+}
+
+#[test]
+fn test_write_str() {
+	let mut out = Vec::<u8>::new();
+	write_str(&mut out, b"\"$@\"\\\n\x1b\xff");
+	assert_eq!(String::from_utf8(out).unwrap(), "\"\\\"$@\\\"\\\\\\n\\u001b\u{fffd}\"");
+}
+
+#[test]
+fn test_parse() {
+	let value = parse(br#" {"id": 1, "params": {"text": "a\n\u00e6\ud83d\ude00", "list": [true, null, -2.5e1]}} "#).ok().unwrap();
+	assert_eq!(value.get("id").as_uint(), Some(1));
+	assert_eq!(value.get("params").get("text").as_str(), Some("a\næ😀"));
+	assert_eq!(value.get("params").get("list").as_array().len(), 3);
+	assert!(value.get("missing").as_str().is_none());
+	let mut out = Vec::<u8>::new();
+	write_value(&mut out, value.get("params").get("list"));
+	assert_eq!(out, b"[true,null,-25]");
+	assert!(parse(b"{\"a\":}").is_err());
+	assert!(parse(b"[1] x").is_err());
+}
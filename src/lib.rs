@@ -0,0 +1,394 @@
+/*
+ * Copyright 2016 - 2024 Andreas Nordal
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+// Color codes are split between flag- and color bits on purpose, such as 0x03_789060.
+#![allow(clippy::unusual_byte_groupings)]
+
+mod annotate;
+mod cli;
+mod config;
+mod errfmt;
+mod explain;
+mod filestream;
+mod ignore;
+mod json;
+mod lsp;
+mod machine;
+mod parallel;
+mod patch;
+mod replace;
+mod report;
+mod rule;
+mod sarif;
+mod situation;
+mod toml;
+mod walk;
+
+use crate::filestream::OutputSink;
+
+pub use crate::errfmt::ContextualError;
+pub use crate::machine::Edit;
+pub use crate::machine::Error;
+pub use crate::machine::OutputSelector;
+pub use crate::machine::Settings;
+pub use crate::rule::Rule;
+
+// For the binary, which is the command line interface.
+#[doc(hidden)]
+pub use crate::cli::main as cli_main;
+
+/// The result of hardening a script in memory.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Default)]
+pub struct Hardened {
+	/// What the selected output mode would have printed.
+	pub output: Vec<u8>,
+	/// Whether any changes are suggested.
+	pub change: bool,
+	/// The suggested changes, in order of appearance.
+	pub edits: Vec<Edit>,
+	/// Syntax errors, in order of appearance.
+	/// The parts in error are left as they are, and the rest is hardened as usual.
+	pub errors: Vec<ContextualError>,
+	/// Directives in comments that disable nothing, in order of appearance.
+	pub warnings: Vec<ContextualError>,
+}
+
+/// Run shellharden on a script held in memory.
+///
+/// To get the rewritten script, use `Settings::transform()`:
+///
+/// ```
+/// let hardened = shellharden::harden(b"echo $a\n", &shellharden::Settings::transform())?;
+/// assert_eq!(hardened.output, b"echo \"$a\"\n");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// Otherwise, the output depends on `sett.osel` just like for files:
+/// `Settings::default()` is for the terminal, with a colored diff.
+/// With `OutputSelector::Check`, it is empty.
+///
+/// Syntax errors don't fail it; they are in `Hardened::errors`.
+/// The output goes to memory, where writing it should not fail,
+/// but if it does, the I/O error is returned.
+pub fn harden(input: &[u8], sett: &Settings) -> std::io::Result<Hardened> {
+	let (fo, res) = machine::treatbytes(input, sett);
+	let errors = match res {
+		Ok(()) | Err(Error::Check) => Vec::new(),
+		Err(Error::Syntax(errors)) => errors,
+		Err(Error::Stdio(e)) => return Err(e),
+	};
+	Ok(Hardened {
+		output: match fo.sink {
+			OutputSink::Soak(vec) => vec,
+			OutputSink::Stdout(_) | OutputSink::None => Vec::new(),
+		},
+		change: fo.change,
+		edits: fo.edits,
+		errors,
+		warnings: fo.warnings,
+	})
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+#[macro_use]
+mod testhelpers;
+
+mod commonargcmd;
+mod commonstrcmd;
+mod microparsers;
+mod sitcase;
+mod sitcmd;
+mod sitcomment;
+mod sitextent;
+mod sitfor;
+mod sitmagic;
+mod sitrvalue;
+mod sitstrdq;
+mod sitstrphantom;
+mod sitstrsqesc;
+mod sittest;
+mod situntilbyte;
+mod sitvarbrace;
+mod sitvarident;
+mod sitvec;
+
+
+#[test]
+fn test_harden() {
+	let res = harden(b"echo $a `pwd`\n", &Settings::transform()).unwrap();
+	assert!(res.change);
+	assert_eq!(res.output, b"echo \"$a\" \"$PWD\"\n");
+	let edits: Vec<(usize, usize, &[u8], Rule, usize)> = res.edits.iter().map(|e| {
+		(e.pos, e.len, e.replacement, e.rule, e.group)
+	}).collect();
+	assert_eq!(edits, vec![
+		(5, 0, &b"\""[..], Rule::Quote, 0),
+		(7, 0, &b"\""[..], Rule::Quote, 0),
+		(8, 0, &b"\""[..], Rule::Quote, 1),
+		(8, 5, &b"$PWD"[..], Rule::Pwd, 2),
+		(13, 0, &b"\""[..], Rule::Quote, 1),
+	]);
+
+	let res = harden(b"echo \"$a\"\n", &Settings::transform()).unwrap();
+	assert!(!res.change);
+	assert_eq!(res.output, b"echo \"$a\"\n");
+
+	let check = Settings {
+		osel: OutputSelector::Check,
+		syntax: false,
+		..Settings::default()
+	};
+	let res = harden(b"echo $a\n", &check).unwrap();
+	assert!(res.change);
+	assert!(res.output.is_empty());
+	let res = harden(b"echo $a\necho $10\n", &check).unwrap();
+	assert_eq!(res.errors.len(), 1);
+
+	let res = harden(b"echo $10 $a\necho $11\n", &Settings::transform()).unwrap();
+	assert_eq!(res.output, b"echo $10 \"$a\"\necho $11\n");
+	assert_eq!(res.edits.len(), 2);
+	let errors: Vec<(usize, usize)> = res.errors.iter().map(|e| (e.line, e.col)).collect();
+	assert_eq!(errors, vec![(1, 6), (2, 6)]);
+	assert_eq!(res.errors[0].typ, "Unsupported syntax: Syntactic pitfall");
+}
+
+#[test]
+fn test_harden_edits() {
+	let input = b"echo $* ${a} ${b[*]}\n[ -z $d ] && [ x$e = xyes ]\n";
+	let res = harden(input, &Settings::transform()).unwrap();
+	assert_eq!(res.output, b"echo \"$@\" \"$a\" \"${b[@]}\"\n[ \"$d\" = \"\" ] && [ \"$e\" = yes ]\n");
+
+	let mut rules: Vec<Rule> = res.edits.iter().map(|e| e.rule).collect();
+	rules.dedup();
+	assert_eq!(rules, vec![
+		Rule::DollarStar,
+		Rule::Quote,
+		Rule::VarBrace,
+		Rule::Quote,
+		Rule::ArrayStar,
+		Rule::Quote,
+		Rule::TestEmptiness,
+		Rule::Quote,
+		Rule::TestEmptiness,
+		Rule::TestXyes,
+		Rule::Quote,
+		Rule::TestXyes,
+	]);
+
+	// In order, the edits make the output.
+	let mut output = Vec::new();
+	let mut pos = 0;
+	for e in &res.edits {
+		assert!(e.pos >= pos);
+		output.extend_from_slice(&input[pos..e.pos]);
+		output.extend_from_slice(e.replacement);
+		pos = e.pos + e.len;
+	}
+	output.extend_from_slice(&input[pos..]);
+	assert_eq!(output, res.output);
+}
+
+#[test]
+fn test_harden_recovery() {
+	let input = b"echo $10 $a\necho `pwd` $b\necho $11 $c\necho \"$d $((1 +\n";
+	let res = harden(input, &Settings::transform()).unwrap();
+	assert!(res.change);
+	assert_eq!(res.output, b"echo $10 \"$a\"\necho \"$PWD\" \"$b\"\necho $11 \"$c\"\necho \"$d $((1 +\n");
+	let errors: Vec<(usize, usize, &str)> = res.errors.iter().map(|e| (e.line, e.col, e.typ)).collect();
+	assert_eq!(errors, vec![
+		(1, 6, "Unsupported syntax: Syntactic pitfall"),
+		(3, 6, "Unsupported syntax: Syntactic pitfall"),
+		(5, 1, "Unexpected end of file"),
+	]);
+	let rules: Vec<Rule> = res.edits.iter().map(|e| e.rule).collect();
+	assert_eq!(rules, vec![
+		Rule::Quote,
+		Rule::Quote,
+		Rule::Quote,
+		Rule::Pwd,
+		Rule::Quote,
+		Rule::Quote,
+		Rule::Quote,
+		Rule::Quote,
+		Rule::Quote,
+	]);
+}
+
+#[test]
+fn test_harden_disabled() {
+	let mut sett = Settings::transform();
+	sett.enable(Rule::TestEmptiness, false);
+	sett.enable(Rule::Backtick, false);
+	let res = harden(b"[ -n $a ] && echo `date` $*\n", &sett).unwrap();
+	assert_eq!(res.output, b"[ -n \"$a\" ] && echo \"`date`\" \"$@\"\n");
+	assert!(res.edits.iter().all(|e| sett.is_enabled(e.rule)));
+
+	// Without quotes, the operand could vanish, so the test is left as it is.
+	let script = b"[ -z $a ] && [ -n $b ] && [ -z \"$c\" ]\n";
+	let mut sett = Settings::transform();
+	sett.enable(Rule::Quote, false);
+	assert_eq!(harden(script, &sett).unwrap().output, b"[ -z $a ] && [ -n $b ] && [ \"$c\" = \"\" ]\n");
+	sett.enable(Rule::Quote, true);
+	sett.enable(Rule::TestNUnquoted, false);
+	assert_eq!(harden(script, &sett).unwrap().output, b"[ \"$a\" = \"\" ] && [ \"$b\" != \"\" ] && [ \"$c\" = \"\" ]\n");
+	sett.unbreak = true;
+	assert_eq!(harden(script, &sett).unwrap().output, b"[ \"$a\" = \"\" ] && [ -n $b ] && [ \"$c\" = \"\" ]\n");
+
+	for rule in rule::ALL {
+		sett.enable(rule, false);
+	}
+	let res = harden(b"[ -n $a ] && echo `pwd` $*\n", &sett).unwrap();
+	assert!(!res.change);
+	assert_eq!(res.output, b"[ -n $a ] && echo `pwd` $*\n");
+}
+
+#[test]
+fn test_harden_directives() {
+	let script = b"\
+		# shellharden disable=quote-var\n\
+		\n\
+		echo $a \\\n\
+		\t$b; echo $c\n\
+		echo $d\n\
+		if true; then # shellharden disable=quote-var\n\
+		\techo $e\n\
+		fi\n\
+		echo $f # shellharden disable=quote-var\n\
+		echo $g\n\
+		x=$(true # shellharden disable=quote-var\n\
+		)\n\
+		echo $h\n\
+		# shellharden disable-file=test-emptiness\n\
+		[ -n $i ]\n\
+	";
+	let res = harden(script, &Settings::transform()).unwrap();
+	assert_eq!(String::from_utf8_lossy(&res.output), "\
+		# shellharden disable=quote-var\n\
+		\n\
+		echo $a \\\n\
+		\t$b; echo $c\n\
+		echo \"$d\"\n\
+		if true; then # shellharden disable=quote-var\n\
+		\techo $e\n\
+		fi\n\
+		echo \"$f\" # shellharden disable=quote-var\n\
+		echo $g\n\
+		x=$(true # shellharden disable=quote-var\n\
+		)\n\
+		echo $h\n\
+		# shellharden disable-file=test-emptiness\n\
+		[ -n \"$i\" ]\n\
+	");
+	assert!(res.warnings.is_empty());
+
+	let script = b"# shellharden disable=quote-var\n[ -z $a ] && [ -n $a ]\n[ -z $b ]\n";
+	let res = harden(script, &Settings::transform()).unwrap();
+	assert_eq!(res.output, b"# shellharden disable=quote-var\n[ -z $a ] && [ -n $a ]\n[ \"$b\" = \"\" ]\n");
+}
+
+#[test]
+fn test_harden_directive_warnings() {
+	let script = b"\
+		# shellharden disable=quote-var,nope\n\
+		echo $a\n\
+		# shellharden disabel=pwd\n\
+		# shellharden disable=pwd\n\
+	";
+	let res = harden(script, &Settings::transform()).unwrap();
+	assert_eq!(res.output, script);
+	let warnings: Vec<(&str, usize, usize, usize)> = res.warnings.iter().map(|w| (w.id, w.line, w.col, w.len)).collect();
+	assert_eq!(warnings, vec![
+		("directive-unknown-rule", 1, 33, 4),
+		("directive-no-effect", 3, 3, 23),
+		("directive-unused", 4, 2, 24),
+	]);
+}
+
+#[test]
+fn test_harden_unbreak() {
+	let script = b"echo $10 \"$12\"\n[ -n $a ]\n";
+	assert_eq!(harden(script, &Settings::transform()).unwrap().errors[0].id, "doubledigit");
+
+	let mut sett = Settings::transform();
+	sett.unbreak = true;
+	let res = harden(script, &sett).unwrap();
+	assert_eq!(res.output, b"echo \"${10}\" \"${12}\"\n[ \"$a\" != \"\" ]\n");
+	let rules: Vec<Rule> = res.edits.iter().map(|e| e.rule).collect();
+	assert_eq!(rules, vec![
+		Rule::Quote,
+		Rule::PositionalBraces,
+		Rule::PositionalBraces,
+		Rule::Quote,
+		Rule::PositionalBraces,
+		Rule::PositionalBraces,
+		Rule::TestEmptiness,
+		Rule::TestNUnquoted,
+		Rule::TestNUnquoted,
+		Rule::TestEmptiness,
+	]);
+
+	sett.enable(Rule::PositionalBraces, false);
+	assert_eq!(harden(script, &sett).unwrap().errors.len(), 2);
+
+	// One run is enough: A second has nothing more to fix.
+	sett.enable(Rule::PositionalBraces, true);
+	let script = b"[ -n $10 ]\n[ -z $10$10 ]\necho $10 \"$11\"\n";
+	let once = harden(script, &sett).unwrap();
+	assert!(once.errors.is_empty());
+	assert_eq!(once.output, b"[ \"${10}\" != \"\" ]\n[ \"${10}${10}\" = \"\" ]\necho \"${10}\" \"${11}\"\n");
+	let twice = harden(&once.output, &sett).unwrap();
+	assert!(!twice.change);
+	assert_eq!(twice.output, once.output);
+
+	// Without --unbreak, the quotes that -n needs are just quoting.
+	let rules: Vec<Rule> = harden(b"[ -n $a ]\n", &Settings::transform()).unwrap().edits.iter().map(|e| e.rule).collect();
+	assert_eq!(rules, vec![Rule::TestEmptiness, Rule::Quote, Rule::Quote, Rule::TestEmptiness]);
+}
+
+#[test]
+fn test_harden_varbraces() {
+	let script = b"echo ${a} $b \"x$c\"\n";
+	let mut sett = Settings::transform();
+	assert_eq!(harden(script, &sett).unwrap().output, b"echo \"$a\" \"$b\" \"x$c\"\n");
+	sett.enable(Rule::VarBrace, false);
+	assert_eq!(harden(script, &sett).unwrap().output, b"echo \"${a}\" \"$b\" \"x$c\"\n");
+	sett.enable(Rule::VarBrace, true);
+	sett.enable(Rule::AddVarBraces, true);
+	assert_eq!(harden(script, &sett).unwrap().output, b"echo \"${a}\" \"${b}\" \"x${c}\"\n");
+
+	// A second run has nothing more to add.
+	let script = b"echo ${a} $b \"x$c\" `pwd` $(pwd)x \"$(pwd)/$d\"\n";
+	let once = harden(script, &sett).unwrap().output;
+	assert_eq!(once, b"echo \"${a}\" \"${b}\" \"x${c}\" \"${PWD}\" \"${PWD}\"x \"${PWD}/${d}\"\n");
+	let twice = harden(&once, &sett).unwrap();
+	assert!(!twice.change);
+	assert_eq!(twice.output, once);
+}
+
+#[test]
+fn test_harden_error_position() {
+	let mut script = "# æøå\n".repeat(100).into_bytes();
+	script.extend_from_slice("echo a\necho \"b\"\necho \"æ\" $10\n".as_bytes());
+	let e = harden(&script, &Settings::transform()).unwrap().errors.remove(0);
+	assert_eq!((e.line, e.col), (103, 10));
+	assert_eq!(e.offset, script.len() - 4);
+	assert!(e.ctx[.. e.pos].ends_with("echo a\necho \"b\"\necho \"æ\" ".as_bytes()));
+}
+
+#[test]
+fn test_harden_without_newline() {
+	for script in ["echo $a", "echo ${a}", "echo $(pwd)", "echo x${a}", "echo \"x${a}\""] {
+		let with_newline = harden(format!("{}\n", script).as_bytes(), &Settings::transform()).unwrap().output;
+		let without = harden(script.as_bytes(), &Settings::transform()).unwrap().output;
+		assert_eq!(without, &with_newline[.. with_newline.len() - 1]);
+	}
+}
@@ -0,0 +1,395 @@
+/*
+ * Copyright 2024 Andreas Nordal
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+// Language Server Protocol over stdio: Diagnostics and code actions.
+// Documents are synced in full, and hardened anew on every change.
+
+use std::collections::HashMap;
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+
+use crate::errfmt::ContextualError;
+use crate::json;
+use crate::json::write_str;
+use crate::json::write_uint;
+use crate::json::Value;
+use crate::machine::Edit;
+use crate::machine::OutputSelector;
+use crate::machine::Settings;
+use crate::report::Lines;
+use crate::report::extent;
+use crate::report::groups;
+
+const SEVERITY_ERROR :usize = 1;
+const SEVERITY_WARNING :usize = 2;
+
+struct Server {
+	sett: Settings,
+	documents: HashMap<String, Vec<u8>>,
+	shutdown: bool,
+}
+
+// Returns whether the client asked to shut down before exiting.
+pub fn serve(sett: &Settings) -> io::Result<bool> {
+	let stdin = io::stdin();
+	let mut input = stdin.lock();
+	let stdout = io::stdout();
+	let mut server = Server {
+		sett: Settings {
+			osel: OutputSelector::Transform,
+			syntax: false,
+			replace: false,
+			..sett.clone()
+		},
+		documents: HashMap::new(),
+		shutdown: false,
+	};
+	while let Some(content) = read_message(&mut input)? {
+		let mut replies = Vec::<Vec<u8>>::new();
+		let exit = match json::parse(&content) {
+			Ok(message) => server.handle(&message, &mut replies),
+			Err(_) => {
+				replies.push(error_response(&Value::Null, -32700, "Parse error"));
+				false
+			}
+		};
+		let mut out = stdout.lock();
+		for reply in &replies {
+			write!(out, "Content-Length: {}\r\n\r\n", reply.len())?;
+			out.write_all(reply)?;
+		}
+		out.flush()?;
+		if exit {
+			return Ok(server.shutdown);
+		}
+	}
+	Ok(false)
+}
+
+// None at the end of the input.
+fn read_message(input: &mut dyn BufRead) -> io::Result<Option<Vec<u8>>> {
+	let mut len: Option<usize> = None;
+	loop {
+		let mut header = String::new();
+		if input.read_line(&mut header)? == 0 {
+			return Ok(None);
+		}
+		let header = header.trim_end();
+		if header.is_empty() {
+			break;
+		}
+		if let Some((name, value)) = header.split_once(':') {
+			if name.eq_ignore_ascii_case("Content-Length") {
+				len = value.trim().parse().ok();
+			}
+		}
+	}
+	let len = len.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing Content-Length"))?;
+	let mut content = vec![0; len];
+	input.read_exact(&mut content)?;
+	Ok(Some(content))
+}
+
+impl Server {
+	// Returns whether to exit.
+	fn handle(&mut self, message: &Value, replies: &mut Vec<Vec<u8>>) -> bool {
+		let id = message.get("id");
+		let params = message.get("params");
+		let uri = params.get("textDocument").get("uri").as_str().unwrap_or("");
+		match message.get("method").as_str() {
+			Some("initialize") => {
+				let mut result = Vec::<u8>::new();
+				result.extend_from_slice(b"{\"capabilities\":{\
+					\"textDocumentSync\":1,\
+					\"codeActionProvider\":{\"codeActionKinds\":[\"quickfix\",\"source.fixAll\"]}\
+					},\"serverInfo\":{\"name\":\"shellharden\",\"version\":");
+				write_str(&mut result, env!("CARGO_PKG_VERSION").as_bytes());
+				result.extend_from_slice(b"}}");
+				replies.push(response(id, &result));
+			}
+			Some("shutdown") => {
+				self.shutdown = true;
+				replies.push(response(id, b"null"));
+			}
+			Some("exit") => {
+				return true;
+			}
+			Some("textDocument/didOpen") => {
+				let text = params.get("textDocument").get("text").as_str().unwrap_or("");
+				self.documents.insert(uri.to_owned(), text.as_bytes().to_owned());
+				replies.push(self.diagnostics(uri));
+			}
+			Some("textDocument/didChange") => {
+				// With full sync, the last change is the whole document.
+				if let Some(text) = params.get("contentChanges").as_array().last()
+					.and_then(|change| change.get("text").as_str())
+				{
+					self.documents.insert(uri.to_owned(), text.as_bytes().to_owned());
+					replies.push(self.diagnostics(uri));
+				}
+			}
+			Some("textDocument/didClose") => {
+				self.documents.remove(uri);
+				replies.push(publish_diagnostics(uri, b""));
+			}
+			Some("textDocument/codeAction") => {
+				let result = self.code_actions(uri, params);
+				replies.push(response(id, &result));
+			}
+			Some(_) if !matches!(id, Value::Null) => {
+				replies.push(error_response(id, -32601, "Method not found"));
+			}
+			_ => {}
+		}
+		false
+	}
+
+	fn harden(&self, uri: &str) -> (&[u8], Vec<Edit>, Vec<ContextualError>, Vec<ContextualError>) {
+		let input = self.documents.get(uri).map(|text| &text[..]).unwrap_or(b"");
+		// Nothing to tell if writing to memory fails.
+		let hardened = crate::harden(input, &self.sett).unwrap_or_default();
+		(input, hardened.edits, hardened.errors, hardened.warnings)
+	}
+
+	fn diagnostics(&self, uri: &str) -> Vec<u8> {
+		let (input, edits, errors, warnings) = self.harden(uri);
+		let lines = Lines::new(input);
+		let mut diagnostics = Vec::<u8>::new();
+		for group in groups(&edits) {
+			let rule = group[0].rule;
+			write_diagnostic(&mut diagnostics, input, &lines, extent(&group), SEVERITY_WARNING, rule.id(), rule.summary());
+		}
+		let errors = errors.iter().map(|e| (e, SEVERITY_ERROR));
+		let warnings = warnings.iter().map(|e| (e, SEVERITY_WARNING));
+		for (e, severity) in errors.chain(warnings) {
+			let begin = e.offset.min(input.len());
+			let end = (e.offset + e.len).min(input.len());
+			write_diagnostic(&mut diagnostics, input, &lines, (begin, end), severity, e.id, e.typ);
+		}
+		publish_diagnostics(uri, &diagnostics)
+	}
+
+	// A quick fix for each group of edits in the range, and one to apply them all.
+	fn code_actions(&self, uri: &str, params: &Value) -> Vec<u8> {
+		let (input, edits, _, _) = self.harden(uri);
+		let lines = Lines::new(input);
+		let range = params.get("range");
+		let range_begin = offset_of(input, &lines, range.get("start"));
+		let range_end = offset_of(input, &lines, range.get("end"));
+		let only = params.get("context").get("only");
+
+		let mut actions = Vec::<u8>::new();
+		if is_wanted(only, "quickfix") {
+			for group in groups(&edits) {
+				let (begin, end) = extent(&group);
+				if begin <= range_end && range_begin <= end {
+					write_action(&mut actions, uri, input, &lines, group[0].rule.summary(), "quickfix", &group);
+				}
+			}
+		}
+		if !edits.is_empty() && is_wanted(only, "source.fixAll") {
+			let all: Vec<&Edit> = edits.iter().collect();
+			write_action(&mut actions, uri, input, &lines, "Apply all suggested changes", "source.fixAll", &all);
+		}
+		let mut result = vec![b'['];
+		result.extend_from_slice(&actions);
+		result.push(b']');
+		result
+	}
+}
+
+// Code action kinds are hierarchical, like "source" and "source.fixAll".
+fn is_wanted(only: &Value, kind: &str) -> bool {
+	match only {
+		Value::Array(kinds) => kinds.iter().filter_map(|k| k.as_str()).any(|k| {
+			kind == k || kind.starts_with(&format!("{}.", k))
+		}),
+		_ => true,
+	}
+}
+
+fn response(id: &Value, result: &[u8]) -> Vec<u8> {
+	let mut out = Vec::<u8>::new();
+	out.extend_from_slice(b"{\"jsonrpc\":\"2.0\",\"id\":");
+	json::write_value(&mut out, id);
+	out.extend_from_slice(b",\"result\":");
+	out.extend_from_slice(result);
+	out.extend_from_slice(b"}");
+	out
+}
+
+fn error_response(id: &Value, code: i32, message: &str) -> Vec<u8> {
+	let mut out = Vec::<u8>::new();
+	out.extend_from_slice(b"{\"jsonrpc\":\"2.0\",\"id\":");
+	json::write_value(&mut out, id);
+	out.extend_from_slice(format!(",\"error\":{{\"code\":{},\"message\":", code).as_bytes());
+	write_str(&mut out, message.as_bytes());
+	out.extend_from_slice(b"}}");
+	out
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: &[u8]) -> Vec<u8> {
+	let mut out = Vec::<u8>::new();
+	out.extend_from_slice(b"{\"jsonrpc\":\"2.0\",\"method\":\"textDocument/publishDiagnostics\",\"params\":{\"uri\":");
+	write_str(&mut out, uri.as_bytes());
+	out.extend_from_slice(b",\"diagnostics\":[");
+	out.extend_from_slice(diagnostics);
+	out.extend_from_slice(b"]}}");
+	out
+}
+
+fn write_diagnostic(
+	out: &mut Vec<u8>,
+	input: &[u8],
+	lines: &Lines,
+	(begin, end): (usize, usize),
+	severity: usize,
+	code: &str,
+	message: &str,
+) {
+	if !out.is_empty() {
+		out.push(b',');
+	}
+	out.extend_from_slice(b"{\"range\":");
+	write_range(out, input, lines, begin, end);
+	out.extend_from_slice(b",\"severity\":");
+	write_uint(out, severity);
+	out.extend_from_slice(b",\"code\":");
+	write_str(out, code.as_bytes());
+	out.extend_from_slice(b",\"source\":\"shellharden\",\"message\":");
+	write_str(out, message.as_bytes());
+	out.extend_from_slice(b"}");
+}
+
+fn write_action(
+	out: &mut Vec<u8>,
+	uri: &str,
+	input: &[u8],
+	lines: &Lines,
+	title: &str,
+	kind: &str,
+	edits: &[&Edit],
+) {
+	if !out.is_empty() {
+		out.push(b',');
+	}
+	out.extend_from_slice(b"{\"title\":");
+	write_str(out, title.as_bytes());
+	out.extend_from_slice(b",\"kind\":");
+	write_str(out, kind.as_bytes());
+	out.extend_from_slice(b",\"edit\":{\"changes\":{");
+	write_str(out, uri.as_bytes());
+	out.extend_from_slice(b":[");
+	for (i, edit) in edits.iter().enumerate() {
+		if i != 0 {
+			out.push(b',');
+		}
+		out.extend_from_slice(b"{\"range\":");
+		write_range(out, input, lines, edit.pos, edit.pos + edit.len);
+		out.extend_from_slice(b",\"newText\":");
+		write_str(out, edit.replacement);
+		out.extend_from_slice(b"}");
+	}
+	out.extend_from_slice(b"]}}}");
+}
+
+fn write_range(out: &mut Vec<u8>, input: &[u8], lines: &Lines, begin: usize, end: usize) {
+	out.extend_from_slice(b"{\"start\":");
+	write_position(out, input, lines, begin);
+	out.extend_from_slice(b",\"end\":");
+	write_position(out, input, lines, end);
+	out.extend_from_slice(b"}");
+}
+
+// Lines count from 0, and characters are UTF-16 code units.
+fn write_position(out: &mut Vec<u8>, input: &[u8], lines: &Lines, pos: usize) {
+	let (line, start) = lines.line_start(pos);
+	let character = String::from_utf8_lossy(&input[start .. pos]).encode_utf16().count();
+	out.extend_from_slice(b"{\"line\":");
+	write_uint(out, line);
+	out.extend_from_slice(b",\"character\":");
+	write_uint(out, character);
+	out.extend_from_slice(b"}");
+}
+
+fn offset_of(input: &[u8], lines: &Lines, position: &Value) -> usize {
+	let start = lines.start_of(position.get("line").as_uint().unwrap_or(0));
+	let end = lines.start_of(position.get("line").as_uint().unwrap_or(0) + 1);
+	let character = position.get("character").as_uint().unwrap_or(0);
+	let mut units = 0;
+	for (i, c) in String::from_utf8_lossy(&input[start .. end]).char_indices() {
+		if units >= character {
+			return start + i;
+		}
+		units += c.len_utf16();
+	}
+	end
+}
+
+#[cfg(test)]
+fn session(messages: &[&str]) -> Vec<String> {
+	let mut server = Server {
+		sett: Settings::transform(),
+		documents: HashMap::new(),
+		shutdown: false,
+	};
+	let mut replies = Vec::<Vec<u8>>::new();
+	for message in messages {
+		server.handle(&json::parse(message.as_bytes()).ok().unwrap(), &mut replies);
+	}
+	replies.into_iter().map(|reply| String::from_utf8(reply).unwrap()).collect()
+}
+
+#[test]
+fn test_read_message() {
+	let mut input: &[u8] = b"Content-Length: 2\r\nContent-Type: x\r\n\r\n{}Content-Length: 1\r\n\r\n1";
+	assert_eq!(read_message(&mut input).ok().unwrap(), Some(b"{}".to_vec()));
+	assert_eq!(read_message(&mut input).ok().unwrap(), Some(b"1".to_vec()));
+	assert_eq!(read_message(&mut input).ok().unwrap(), None);
+}
+
+#[test]
+fn test_diagnostics() {
+	let replies = session(&[
+		r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///x.sh","text":"x=1\necho 😀$a `pwd`\n"}}}"#,
+		r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///x.sh"},"contentChanges":[{"text":"echo $10\n"}]}}"#,
+	]);
+	assert_eq!(replies[0], concat!(
+		r#"{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///x.sh","diagnostics":["#,
+		r#"{"range":{"start":{"line":1,"character":7},"end":{"line":1,"character":9}},"severity":2,"code":"quote-var","source":"shellharden","message":"Quote to prevent word splitting and pathname expansion"},"#,
+		r#"{"range":{"start":{"line":1,"character":10},"end":{"line":1,"character":15}},"severity":2,"code":"quote-var","source":"shellharden","message":"Quote to prevent word splitting and pathname expansion"},"#,
+		r#"{"range":{"start":{"line":1,"character":10},"end":{"line":1,"character":15}},"severity":2,"code":"pwd","source":"shellharden","message":"Use $PWD instead of running pwd"}"#,
+		r#"]}}"#,
+	));
+	assert!(replies[1].contains(r#""range":{"start":{"line":0,"character":5},"end":{"line":0,"character":8}},"severity":1,"code":"doubledigit""#));
+}
+
+#[test]
+fn test_code_actions() {
+	let replies = session(&[
+		r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"u","text":"echo $a $b\n"}}}"#,
+		r#"{"jsonrpc":"2.0","id":7,"method":"textDocument/codeAction","params":{"textDocument":{"uri":"u"},"range":{"start":{"line":0,"character":9},"end":{"line":0,"character":9}},"context":{"diagnostics":[]}}}"#,
+		r#"{"jsonrpc":"2.0","id":"x","method":"nonsense"}"#,
+	]);
+	assert_eq!(replies[1], concat!(
+		r#"{"jsonrpc":"2.0","id":7,"result":["#,
+		r#"{"title":"Quote to prevent word splitting and pathname expansion","kind":"quickfix","edit":{"changes":{"u":["#,
+		r#"{"range":{"start":{"line":0,"character":8},"end":{"line":0,"character":8}},"newText":"\""},"#,
+		r#"{"range":{"start":{"line":0,"character":10},"end":{"line":0,"character":10}},"newText":"\""}"#,
+		r#"]}}},"#,
+		r#"{"title":"Apply all suggested changes","kind":"source.fixAll","edit":{"changes":{"u":["#,
+		r#"{"range":{"start":{"line":0,"character":5},"end":{"line":0,"character":5}},"newText":"\""},"#,
+		r#"{"range":{"start":{"line":0,"character":7},"end":{"line":0,"character":7}},"newText":"\""},"#,
+		r#"{"range":{"start":{"line":0,"character":8},"end":{"line":0,"character":8}},"newText":"\""},"#,
+		r#"{"range":{"start":{"line":0,"character":10},"end":{"line":0,"character":10}},"newText":"\""}"#,
+		r#"]}}}"#,
+		r#"]}"#,
+	));
+	assert_eq!(replies[2], r#"{"jsonrpc":"2.0","id":"x","error":{"code":-32601,"message":"Method not found"}}"#);
+}
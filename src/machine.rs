@@ -9,15 +9,23 @@
 use std::io;
 use std::io::Write;
 
+use crate::annotate::Notes;
 use crate::errfmt::ContextualError;
+use crate::errfmt::CONTEXT_LINES;
+use crate::errfmt::codepoints;
+use crate::rule::Rule;
 
 use crate::filestream::InputSource;
 use crate::filestream::FileOut;
 use crate::filestream::OutputSink;
 
+use crate::situation::Alt;
 use crate::situation::Horizon;
+use crate::situation::Problem;
 use crate::situation::Situation;
 use crate::situation::Transition;
+use crate::situation::UnsupportedSyntax;
+use crate::situation::WhatNow;
 use crate::situation::COLOR_NORMAL;
 
 use crate::sitcmd::SitNormal;
@@ -30,60 +38,414 @@ pub enum OutputSelector {
 	Diff,
 	Transform,
 	Check,
+	Json,
+	Sarif,
+	Gcc,
+	Patch,
+	List,
+	// Like List, but NUL-terminated.
+	List0,
 }
 
+impl OutputSelector {
+	// Whether the output describes the changes instead of making them.
+	pub fn is_report(self) -> bool {
+		matches!(self,
+			OutputSelector::Json |
+			OutputSelector::Sarif |
+			OutputSelector::Gcc |
+			OutputSelector::Patch |
+			OutputSelector::List |
+			OutputSelector::List0
+		)
+	}
+}
+
+#[derive(Clone)]
 pub struct Settings {
 	pub osel :OutputSelector,
 	pub syntax :bool,
 	pub replace :bool,
+	pub patch_context :usize,
+	// Whether to fix constructs that never work, which are otherwise errors.
+	pub unbreak :bool,
+	// Whether scripts are sh, which has no arrays, rather than bash.
+	pub sh :bool,
+	// Whether to explain the changes under each changed line of the diff.
+	pub annotate :bool,
+	// Whether the modes that have colors use them. None for if stdout is a terminal.
+	pub color :Option<bool>,
+	// Suffix of the backup that --replace keeps of each changed file.
+	pub backup :Option<String>,
+	// Rules whose changes are not suggested.
+	pub disabled :Vec<Rule>,
+}
+
+impl Default for Settings {
+	fn default() -> Settings {
+		Settings {
+			osel: OutputSelector::Diff,
+			syntax: true,
+			replace: false,
+			patch_context: 3,
+			unbreak: false,
+			sh: false,
+			annotate: false,
+			color: Some(true),
+			backup: None,
+			disabled: crate::rule::ALL.iter().copied().filter(|rule| !rule.is_default()).collect(),
+		}
+	}
 }
 
+impl Settings {
+	/// The default settings, but for the rewritten script as output, without colors.
+	/// This is what to give to `harden` to harden a script.
+	pub fn transform() -> Settings {
+		Settings {
+			osel: OutputSelector::Transform,
+			syntax: false,
+			..Settings::default()
+		}
+	}
+	// By the name of the option, without dashes and "format=".
+	// Returns false for an unknown mode.
+	pub fn set_mode(&mut self, mode: &str) -> bool {
+		let (osel, syntax, replace) = match mode {
+			"suggest" => (OutputSelector::Diff, false, false),
+			"syntax" => (OutputSelector::Original, true, false),
+			"syntax-suggest" => (OutputSelector::Diff, true, false),
+			"transform" => (OutputSelector::Transform, false, false),
+			"check" => (OutputSelector::Check, false, false),
+			"json" => (OutputSelector::Json, false, false),
+			"sarif" => (OutputSelector::Sarif, false, false),
+			"gcc" => (OutputSelector::Gcc, false, false),
+			"patch" => (OutputSelector::Patch, false, false),
+			"list" => (OutputSelector::List, false, false),
+			"list0" => (OutputSelector::List0, false, false),
+			"replace" => (OutputSelector::Transform, false, true),
+			_ => return false,
+		};
+		self.osel = osel;
+		self.syntax = syntax;
+		self.replace = replace;
+		true
+	}
+
+	// By the word that --color takes. Returns false for an unknown one.
+	pub fn set_color(&mut self, when: &str) -> bool {
+		self.color = match when {
+			"auto" => None,
+			"always" => Some(true),
+			"never" => Some(false),
+			_ => return false,
+		};
+		true
+	}
+
+	pub fn enable(&mut self, rule: Rule, enabled: bool) {
+		self.disabled.retain(|&r| r != rule);
+		if !enabled {
+			self.disabled.push(rule);
+		}
+	}
+
+	pub fn is_enabled(&self, rule: Rule) -> bool {
+		// Removing braces would undo adding them.
+		if rule == Rule::VarBrace && self.is_enabled(Rule::AddVarBraces) {
+			return false;
+		}
+		// The fix is an array.
+		if rule == Rule::ForInArray && self.sh {
+			return false;
+		}
+		!self.disabled.contains(&rule)
+	}
+}
+
+// A suggested change, in terms of the original input.
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct Edit {
+	pub pos :usize,
+	pub len :usize,
+	pub replacement :&'static [u8],
+	pub rule :Rule,
+	// Edits that only make sense together, like opening and closing quotes,
+	// have the same group number.
+	pub group :usize,
+}
+
+#[derive(Debug)]
 pub enum Error {
 	Stdio(std::io::Error),
-	Syntax(ContextualError),
+	// In order of appearance. The rest of the file is treated as usual.
+	Syntax(Vec<ContextualError>),
 	Check,
 }
 
-pub fn treatfile(path: &std::ffi::OsString, sett: &Settings) -> Result<(), Error> {
+// Output goes to stdout, or to the buffer if given.
+// Returns whether changes are suggested, also if there are errors, and the result.
+// Warnings are added, whatever the result.
+pub fn treatfile(
+	path: &std::ffi::OsString,
+	sett: &Settings,
+	buffer: Option<&mut Vec<u8>>,
+	warnings: &mut Vec<ContextualError>,
+) -> (bool, Result<(), Error>) {
 	let stdin = io::stdin();
 	let mut fi: InputSource = if path.is_empty() {
 		InputSource::open_stdin(&stdin)
 	} else {
-		InputSource::open_file(path).map_err(Error::Stdio)?
+		match InputSource::open_file(path) {
+			Ok(fi) => fi,
+			Err(e) => return (false, Err(Error::Stdio(e))),
+		}
 	};
 
+	if sett.replace && !path.is_empty() && sett.osel != OutputSelector::Check {
+		let size = match fi.size() {
+			Ok(size) => size,
+			Err(e) => return (false, Err(Error::Stdio(e))),
+		};
+		let mut fo = FileOut::open_soak(size * 9 / 8);
+		let res = treat(&mut fi, &mut fo, sett);
+		warnings.append(&mut fo.warnings);
+		let change = fo.change;
+		let res = res.and_then(|()| fo.commit(path, sett.backup.as_deref()).map_err(Error::Stdio));
+		return (change, res);
+	}
 	let stdout = io::stdout();
+	let fo = open_output(&stdout, sett, buffer.is_some());
+	finish(&mut fi, fo, sett, buffer, warnings)
+}
+
+// Like treatfile, but for a script given as a string, so there is no file to replace.
+pub fn treatstring(
+	script: &[u8],
+	sett: &Settings,
+	buffer: Option<&mut Vec<u8>>,
+	warnings: &mut Vec<ContextualError>,
+) -> (bool, Result<(), Error>) {
+	let mut fi = InputSource::open_bytes(script);
+	let stdout = io::stdout();
+	let fo = open_output(&stdout, sett, buffer.is_some());
+	finish(&mut fi, fo, sett, buffer, warnings)
+}
+
+fn open_output<'a>(stdout: &'a io::Stdout, sett: &Settings, buffered: bool) -> FileOut<'a> {
+	if sett.osel == OutputSelector::Check {
+		FileOut::open_none()
+	} else if buffered {
+		FileOut::open_soak(0)
+	} else {
+		FileOut::open_stdout(stdout)
+	}
+}
+
+// Treat and hand over the output, which gets a final newline if cut short by an I/O error.
+fn finish(
+	fi: &mut InputSource,
+	mut fo: FileOut,
+	sett: &Settings,
+	buffer: Option<&mut Vec<u8>>,
+	warnings: &mut Vec<ContextualError>,
+) -> (bool, Result<(), Error>) {
+	let res = treat(fi, &mut fo, sett);
+	let change = fo.change;
+	warnings.append(&mut fo.warnings);
+	match (fo.sink, buffer) {
+		(OutputSink::Stdout(mut stdout), _) => {
+			if let Err(Error::Stdio(_)) = res {
+				let _ = stdout.write_all(b"\n");
+			}
+		}
+		(OutputSink::Soak(vec), Some(out)) => {
+			out.extend_from_slice(&vec);
+			if let Err(Error::Stdio(_)) = res {
+				out.push(b'\n');
+			}
+		}
+		(_, _) => {}
+	}
+	(change, res)
+}
+
+// The output is kept despite syntax errors.
+pub(crate) fn treatbytes(input: &[u8], sett: &Settings) -> (FileOut<'static>, Result<(), Error>) {
+	let mut fi = InputSource::open_bytes(input);
 	let mut fo: FileOut = if sett.osel == OutputSelector::Check {
 		FileOut::open_none()
-	} else if sett.replace && !path.is_empty() {
-		FileOut::open_soak(fi.size().map_err(Error::Stdio)? * 9 / 8)
 	} else {
-		FileOut::open_stdout(&stdout)
+		FileOut::open_soak(input.len() as u64 * 9 / 8)
 	};
+	let res = treat(&mut fi, &mut fo, sett);
+	(fo, res)
+}
 
+fn treat(fi: &mut InputSource, fo: &mut FileOut, sett: &Settings) -> Result<(), Error> {
 	let mut color_cur = COLOR_NORMAL;
+	if sett.annotate && sett.osel == OutputSelector::Diff {
+		fo.notes = Some(Notes::new());
+	}
 
-	let res = treatfile_fallible(&mut fi, &mut fo, &mut color_cur, sett);
+	let res = treatfile_fallible(fi, fo, &mut color_cur, sett);
 	if color_cur != COLOR_NORMAL {
-		write_color(&mut fo, COLOR_NORMAL).map_err(Error::Stdio)?;
-	}
-	if res.is_ok() {
-		fo.commit(path).map_err(Error::Stdio)
-	} else {
-		if let OutputSink::Stdout(mut stdout) = fo.sink {
-			let _ = stdout.write_all(b"\n");
-		}
-		res
+		write_color(fo, COLOR_NORMAL).map_err(Error::Stdio)?;
 	}
+	res
 }
 
 const MAXHORIZON :usize = 128;
+const MAXCONTEXT :usize = 1024;
+
+fn advance((line, col): (usize, usize), text: &[u8]) -> (usize, usize) {
+	match text.iter().rposition(|&c| c == b'\n') {
+		Some(last) => (line + text.iter().filter(|&&c| c == b'\n').count(), codepoints(&text[last + 1 ..])),
+		None => (line, col + codepoints(text)),
+	}
+}
+
+// Where the buffer is in the file, which edit group, if any,
+// each state on the stack is the continuation of,
+// and which rules are disabled by comments.
+struct Tracking {
+	offset :usize,
+	// 0-based line and column of the offset, where the column counts codepoints.
+	linecol :(usize, usize),
+	// The lines before the offset, as context for error messages.
+	recent :Vec<u8>,
+	errors :Vec<ContextualError>,
+	// About directives, which don't stop anything.
+	warnings :Vec<ContextualError>,
+	groups :Vec<Option<(usize, Rule)>>,
+	numgroups :usize,
+	disabled_file :Vec<Rule>,
+	disabled_next :Vec<DisabledNext>,
+}
+
+// For the next line of code after a comment.
+struct DisabledNext {
+	// Of the comment, or of the scope it was in, once that has ended.
+	depth :usize,
+	// Whether code has followed it yet.
+	code :bool,
+	rules :Vec<Rule>,
+	// In case no code follows.
+	unused :ContextualError,
+}
+
+impl Tracking {
+	fn new() -> Tracking {
+		Tracking{
+			offset: 0,
+			linecol: (0, 0),
+			recent: Vec::new(),
+			errors: Vec::new(),
+			warnings: Vec::new(),
+			groups: vec!{None},
+			numgroups: 0,
+			disabled_file: Vec::new(),
+			disabled_next: Vec::new(),
+		}
+	}
+	fn consume(&mut self, consumed: &[u8]) {
+		self.offset += consumed.len();
+		self.linecol = advance(self.linecol, consumed);
+		self.recent.extend_from_slice(consumed);
+		// Lines of context and the line so far, if not too long.
+		let mut newlines = self.recent.iter().rposition(|&c| c == b'\n');
+		for _ in 0 .. CONTEXT_LINES {
+			newlines = newlines.and_then(|i| self.recent[.. i].iter().rposition(|&c| c == b'\n'));
+		}
+		let begin = newlines.map_or(0, |i| i + 1).max(self.recent.len().saturating_sub(MAXCONTEXT));
+		self.recent.drain(.. begin);
+	}
+	fn error(&self, window: &[u8], pos: usize, len: usize, e: UnsupportedSyntax) -> ContextualError {
+		let (line, col) = advance(self.linecol, &window[.. pos]);
+		let mut ctx = self.recent.clone();
+		ctx.extend_from_slice(window);
+		ContextualError{
+			id: e.id,
+			typ: e.typ,
+			ctx,
+			pos: self.recent.len() + pos,
+			len,
+			offset: self.offset + pos,
+			line: line + 1,
+			col: col + 1,
+			msg: e.msg,
+		}
+	}
+	fn is_disabled(&self, rule: Rule) -> bool {
+		self.disabled_file.contains(&rule)
+			|| self.disabled_next.iter().any(|next| next.rules.contains(&rule))
+	}
+	// After a pop at the given position in the window, to the given depth.
+	fn popped(&mut self, popped: &mut dyn Situation, depth: usize, window: &[u8], pos: usize) {
+		match popped.directive() {
+			Some(directive) => {
+				// The comment text, unless it began before the window.
+				let (begin, len) = match pos.checked_sub(directive.len) {
+					Some(begin) => (begin, directive.len),
+					None => (pos, 0),
+				};
+				for &(offset, problem_len, problem) in &directive.problems {
+					let e = if len == 0 {
+						self.error(window, pos, 0, warning(problem))
+					} else {
+						self.error(window, begin + offset, problem_len, warning(problem))
+					};
+					self.warnings.push(e);
+				}
+				self.disabled_file.extend(directive.file);
+				if !directive.next.is_empty() {
+					let unused = self.error(window, begin, len, UnsupportedSyntax{
+						id: "directive-unused",
+						typ: "Warning: Directive without effect",
+						msg: "No line of code follows the directive for it to apply to.",
+						fix: None,
+					});
+					self.disabled_next.push(DisabledNext{depth, code: false, rules: directive.next, unused});
+				}
+			}
+			None => {
+				// A directive that no code has followed yet, like after the code
+				// on the same line, is for the next line of code after the scope.
+				self.disabled_next.retain(|next| next.depth <= depth || !next.code);
+				for next in &mut self.disabled_next {
+					if next.depth > depth {
+						next.depth = depth;
+					} else {
+						next.code |= next.depth == depth;
+					}
+				}
+			}
+		}
+	}
+	// A newline between commands, at the given depth, ends the line of code.
+	// Compound commands, like if and while, are not understood as a whole.
+	fn newline(&mut self, depth: usize) {
+		self.disabled_next.retain(|next| !(next.code && next.depth == depth));
+	}
+	// An edit continues the group of the state that made it, if the rule is the same.
+	fn group_of(&mut self, rule: Rule) -> usize {
+		match self.groups.last() {
+			Some(&Some((group, grouprule))) if grouprule == rule => group,
+			_ => {
+				self.numgroups += 1;
+				self.numgroups - 1
+			}
+		}
+	}
+}
 
 fn treatfile_fallible(
 	fi: &mut InputSource, fo: &mut FileOut,
 	color_cur: &mut u32, sett: &Settings,
 ) -> Result<(), Error> {
 	let mut fill :usize = 0;
+	let mut track = Tracking::new();
 	let mut buf = [0; MAXHORIZON];
 
 	let mut state :Vec<Box<dyn Situation>> = vec!{Box::new(SitNormal {
@@ -96,8 +458,9 @@ fn treatfile_fallible(
 		fill += bytes;
 		let eof = bytes == 0;
 		let consumed = stackmachine(
-			&mut state, fo, color_cur, &buf[0 .. fill], eof, sett
+			&mut state, fo, color_cur, &buf[0 .. fill], &mut track, eof, sett
 		)?;
+		track.consume(&buf[0 .. consumed]);
 		let remain = fill - consumed;
 		if eof {
 			assert!(remain == 0);
@@ -110,23 +473,60 @@ fn treatfile_fallible(
 		fill = remain;
 	}
 	if state.len() != 1 {
-		return Err(Error::Syntax(ContextualError{
+		let e = track.error(&[], 0, 1, UnsupportedSyntax{
+			id: "unexpected-eof",
 			typ: "Unexpected end of file",
-			ctx: buf[0 .. fill].to_owned(),
-			pos: fill,
-			len: 1,
 			msg: "The file's end was reached without closing all sytactic scopes.\n\
 			Either, the parser got lost, or the file is truncated or malformed.",
-		}));
+			fix: None,
+		});
+		track.errors.push(e);
+	}
+	// Not to run into the error messages, and to end the line with its notes.
+	let pending = fo.notes.as_ref().is_some_and(Notes::is_pending);
+	if (pending || !track.errors.is_empty()) && !track.recent.is_empty() && !track.recent.ends_with(b"\n") {
+		write_colored_slice(fo, color_cur, COLOR_NORMAL, b"\n").map_err(Error::Stdio)?;
+	}
+	for next in track.disabled_next.drain(..) {
+		if !next.code {
+			track.warnings.push(next.unused);
+		}
+	}
+	track.warnings.sort_by_key(|w| w.offset);
+	fo.warnings = track.warnings;
+	if !track.errors.is_empty() {
+		return Err(Error::Syntax(track.errors));
+	}
+	if sett.osel == OutputSelector::Check && fo.change {
+		return Err(Error::Check);
 	}
 	Ok(())
 }
 
+fn warning(problem: Problem) -> UnsupportedSyntax {
+	match problem {
+		Problem::UnknownRule => UnsupportedSyntax{
+			id: "directive-unknown-rule",
+			typ: "Warning: No such rule",
+			msg: "The directive can't disable a rule by this id. See --list-rules for the ids.",
+			fix: None,
+		},
+		Problem::NoEffect => UnsupportedSyntax{
+			id: "directive-no-effect",
+			typ: "Warning: Directive without effect",
+			msg: "A shellharden directive is disable=RULE,... for the next line of code,\n\
+			or disable-file=RULE,... for the rest of the file.",
+			fix: None,
+		},
+	}
+}
+
 fn stackmachine(
 	state: &mut Vec<Box<dyn Situation>>,
 	out: &mut FileOut,
 	color_cur: &mut u32,
 	buf: &[u8],
+	track: &mut Tracking,
 	eof: bool,
 	sett: &Settings,
 ) -> Result<usize, Error> {
@@ -136,6 +536,7 @@ fn stackmachine(
 		let horizon = Horizon {
 			input: inputhorizon,
 			is_lengthenable: inputhorizon.len() < MAXHORIZON && !eof,
+			is_eof: eof,
 		};
 		let stacksize_pre = state.len();
 		let statebox: &mut Box<dyn Situation> = if let Some(innerstate) = state.last_mut() {
@@ -145,19 +546,57 @@ fn stackmachine(
 		};
 		let curstate = statebox.as_mut();
 		let color_pre = if sett.syntax { curstate.get_color() } else { COLOR_NORMAL };
-		let whatnow = curstate.whatnow(horizon);
+		let whatnow = match curstate.whatnow(horizon) {
+			WhatNow {
+				transition: Transition::Err(UnsupportedSyntax { fix: Some((rule, fix)), .. }),
+				..
+			} if sett.unbreak && sett.is_enabled(rule) && !track.is_disabled(rule) => *fix,
+			whatnow => whatnow,
+		};
 		let (pre, len, alt) = whatnow.transform;
+		if horizon.input[.. pre].contains(&b'\n') {
+			track.newline(stacksize_pre);
+		}
+		// A rule's edits that only make sense together are all left out together,
+		// and so are those that make no sense without another rule's.
+		let is_enabled = |rule| sett.is_enabled(rule) && !track.is_disabled(rule);
+		// Without --unbreak, the quotes that -n needs are just quoting, as they always were.
+		let as_set = |rule| if rule == Rule::TestNUnquoted && !sett.unbreak { Rule::Quote } else { rule };
+		let alt = alt.map(|replacement| Alt {
+			rule: as_set(replacement.rule),
+			needs: replacement.needs.map(as_set),
+			..replacement
+		}).filter(|replacement| {
+			is_enabled(replacement.rule) && replacement.needs.is_none_or(is_enabled)
+		}).map(|replacement| {
+			// Braces that add-varbraces would otherwise add to $PWD in the next run.
+			if replacement.rule == Rule::Pwd && is_enabled(Rule::AddVarBraces) {
+				Alt { text: b"${PWD}", ..replacement }
+			} else {
+				replacement
+			}
+		});
 
-		if alt.is_some() {
+		let mut group = None;
+		if let Some(replacement) = alt {
+			let id = track.group_of(replacement.rule);
+			group = Some((id, replacement.rule));
 			out.change = true;
-			if sett.osel == OutputSelector::Check {
-				return Err(Error::Check);
-			}
+			out.edits.push(Edit {
+				pos: track.offset + pos + pre,
+				len,
+				replacement: replacement.text,
+				rule: replacement.rule,
+				group: id,
+			});
 		}
 
 		write_colored_slice(
 			out, color_cur, color_pre, &horizon.input[.. pre]
 		).map_err(Error::Stdio)?;
+		if let (Some((id, rule)), Some(notes)) = (group, out.notes.as_mut()) {
+			notes.mark(id, rule);
+		}
 		let progress = pre + len;
 		let replaceable = &horizon.input[pre .. progress];
 
@@ -169,21 +608,23 @@ fn stackmachine(
 			}
 			(Transition::Replace(newstate), _) => {
 				*statebox = newstate;
+				if group.is_some() {
+					*track.groups.last_mut().unwrap() = group;
+				}
 			}
 			(Transition::Push(newstate), _) => {
 				state.push(newstate);
+				track.groups.push(group);
 			}
 			(Transition::Pop, _) | (Transition::FlushPopOnEof, true) => {
-				state.pop();
+				let mut popped = state.pop().unwrap();
+				track.groups.pop();
+				track.popped(popped.as_mut(), state.len(), buf, pos + pre);
 			}
+			// The token in error is left as it is, and skipped.
 			(Transition::Err(e), _) => {
-				return Err(Error::Syntax(ContextualError{
-					typ: e.typ,
-					ctx: buf.to_owned(),
-					pos: pos + whatnow.transform.0,
-					len: whatnow.transform.1,
-					msg: e.msg,
-				}));
+				let e = track.error(buf, pos + pre, len, e);
+				track.errors.push(e);
 			}
 		}
 
@@ -207,14 +648,19 @@ fn write_transition(
 	color_trans: u32,
 	sett: &Settings,
 	replaceable: &[u8],
-	alternative: Option<&[u8]>,
+	alternative: Option<Alt>,
 ) -> Result<(), std::io::Error> {
 	match (alternative, sett.osel) {
 		(Some(replacement), OutputSelector::Diff) => {
-			write_diff(out, color_cur, color_trans, replaceable, replacement)
+			let colors = if replacement.rule.changes_behavior() {
+				(0x10_800080, 0x10_808000)
+			} else {
+				(0x10_800000, 0x10_008000)
+			};
+			write_diff(out, color_cur, color_trans, colors, replaceable, replacement.text)
 		}
 		(Some(replacement), OutputSelector::Transform) => {
-			write_colored_slice(out, color_cur, color_trans, replacement)
+			write_colored_slice(out, color_cur, color_trans, replacement.text)
 		}
 		(_, _) => {
 			write_colored_slice(out, color_cur, color_trans, replaceable)
@@ -228,11 +674,10 @@ fn write_diff(
 	out: &mut FileOut,
 	color_cur: &mut u32,
 	color_neutral: u32,
+	(color_a, color_b): (u32, u32),
 	replaceable: &[u8],
 	replacement: &[u8],
 ) -> Result<(), std::io::Error> {
-	let color_a = 0x10_800000;
-	let color_b = 0x10_008000;
 	let remain_a = replaceable;
 	let mut remain_b = replacement;
 	for (i, &a) in remain_a.iter().enumerate() {
@@ -262,7 +707,28 @@ fn write_colored_slice(
 		write_color(out, color)?;
 		*color_cur = color;
 	}
-	out.write_all(slice)
+	let notes = match out.notes {
+		Some(ref mut notes) => notes,
+		None => return out.write_all(slice),
+	};
+	let line_end = match slice.iter().position(|&c| c == b'\n') {
+		Some(i) => i,
+		None => {
+			notes.extend(slice);
+			return out.write_all(slice);
+		}
+	};
+	notes.extend(&slice[.. line_end]);
+	let footnotes = notes.end_line();
+	out.write_all(&slice[..= line_end])?;
+	if !footnotes.is_empty() {
+		if *color_cur != COLOR_NORMAL {
+			write_color(out, COLOR_NORMAL)?;
+			*color_cur = COLOR_NORMAL;
+		}
+		out.write_all(&footnotes)?;
+	}
+	write_colored_slice(out, color_cur, color, &slice[line_end + 1 ..])
 }
 
 fn write_color(out :&mut FileOut, code :u32) -> Result<(), std::io::Error> {
@@ -285,23 +751,25 @@ fn write_color(out :&mut FileOut, code :u32) -> Result<(), std::io::Error> {
 	}
 }
 
-pub fn expression_tracker(horizon: &[u8], state: Box<dyn Situation>) -> Result<(bool, usize), ()> {
+pub(crate) fn expression_tracker(horizon: &[u8], state: Box<dyn Situation>) -> Result<(bool, usize), ()> {
 	let mut stack = vec!{state};
 	let mut color_cur = COLOR_NORMAL;
+	let mut track = Tracking::new();
 
 	match stackmachine(
 		&mut stack,
 		&mut FileOut::open_none(),
 		&mut color_cur,
 		horizon,
+		&mut track,
 		false,
 		&Settings{
 			osel: OutputSelector::Original,
 			syntax: false,
-			replace: false,
+			..Settings::default()
 		},
 	) {
-		Ok(len) => Ok((stack.is_empty(), len)),
-		Err(_) => Err(()),
+		Ok(len) if track.errors.is_empty() => Ok((stack.is_empty(), len)),
+		Ok(_) | Err(_) => Err(()),
 	}
 }
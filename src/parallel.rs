@@ -0,0 +1,84 @@
+/*
+ * Copyright 2024 Andreas Nordal
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::thread;
+
+// Does the work on each task on a number of threads, and hands the results
+// to `conclude` in the order of the tasks, each as soon as it and those before it are done.
+// Once `conclude` returns false, no more tasks are started, and no more results handed over.
+pub fn run_ordered<T, R, W, C>(tasks: Vec<T>, jobs: usize, work: W, mut conclude: C)
+where
+	T: Send,
+	R: Send,
+	W: Fn(T) -> R + Sync,
+	C: FnMut(R) -> bool,
+{
+	let count = tasks.len();
+	let slots: Vec<Mutex<Option<T>>> = tasks.into_iter().map(|task| Mutex::new(Some(task))).collect();
+	let next = AtomicUsize::new(0);
+	let stop = AtomicBool::new(false);
+
+	thread::scope(|scope| {
+		let (sender, receiver) = mpsc::channel();
+		for _ in 0 .. jobs.min(count) {
+			let sender = sender.clone();
+			let (slots, next, stop, work) = (&slots, &next, &stop, &work);
+			scope.spawn(move || {
+				while !stop.load(Ordering::Relaxed) {
+					let i = next.fetch_add(1, Ordering::Relaxed);
+					if i >= count {
+						break;
+					}
+					let task = slots[i].lock().unwrap().take().unwrap();
+					if sender.send((i, work(task))).is_err() {
+						break;
+					}
+				}
+			});
+		}
+		drop(sender);
+
+		let mut done: HashMap<usize, R> = HashMap::new();
+		let mut i = 0;
+		while i < count {
+			if let Some(result) = done.remove(&i) {
+				if !conclude(result) {
+					stop.store(true, Ordering::Relaxed);
+					break;
+				}
+				i += 1;
+				continue;
+			}
+			match receiver.recv() {
+				Ok((j, result)) => {
+					done.insert(j, result);
+				}
+				Err(_) => break,
+			}
+		}
+	});
+}
+
+#[test]
+fn test_run_ordered() {
+	let mut results = Vec::new();
+	run_ordered((0 .. 100).collect(), 4, |n: u64| {
+		thread::sleep(std::time::Duration::from_micros((n * 7919) % 100));
+		n * n
+	}, |r| {
+		results.push(r);
+		r < 50 * 50
+	});
+	assert_eq!(results, (0 .. 51).map(|n| n * n).collect::<Vec<u64>>());
+}
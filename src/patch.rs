@@ -0,0 +1,190 @@
+/*
+ * Copyright 2024 Andreas Nordal
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+// Unified diff, as accepted by `patch -p1` and `git apply`.
+
+use crate::machine::Edit;
+use crate::report::Lines;
+
+// The edits in their original order, applied to input[begin .. end].
+// An insertion at `end` belongs to what comes after, unless that is nothing.
+pub fn apply_edits(input: &[u8], edits: &[Edit], begin: usize, end: usize) -> Vec<u8> {
+	let mut applied = Vec::with_capacity(end - begin);
+	let mut pos = begin;
+	for edit in edits {
+		if edit.pos < begin || edit.pos + edit.len > end || (edit.pos == end && end < input.len()) {
+			continue;
+		}
+		applied.extend_from_slice(&input[pos .. edit.pos]);
+		applied.extend_from_slice(edit.replacement);
+		pos = edit.pos + edit.len;
+	}
+	applied.extend_from_slice(&input[pos .. end]);
+	applied
+}
+
+// A range of original lines that changes.
+struct Change {
+	first: usize,
+	last: usize,
+	replacement: Vec<u8>,
+}
+
+fn changes(input: &[u8], lines: &Lines, edits: &[Edit]) -> Vec<Change> {
+	let mut ranges: Vec<(usize, usize)> = Vec::new();
+	let lastpos = input.len() - 1;
+	for edit in edits {
+		let (first, _) = lines.line_start(edit.pos.min(lastpos));
+		let (last, _) = lines.line_start((edit.pos + edit.len.max(1) - 1).min(lastpos));
+		match ranges.last_mut() {
+			Some(prev) if first <= prev.1 => prev.1 = prev.1.max(last),
+			_ => ranges.push((first, last)),
+		}
+	}
+	ranges.iter().map(|&(first, last)| {
+		let begin = lines.start_of(first);
+		let end = lines.start_of(last + 1).min(input.len());
+		Change {
+			first,
+			last,
+			replacement: apply_edits(input, edits, begin, end),
+		}
+	}).collect()
+}
+
+pub fn write_patch(out: &mut Vec<u8>, path: &str, input: &[u8], edits: &[Edit], context: usize) {
+	if edits.is_empty() {
+		return;
+	}
+	let lines = Lines::new(input);
+	let numlines = lines.count();
+	let changes = changes(input, &lines, edits);
+
+	let path = path.trim_start_matches("./");
+	out.extend_from_slice(format!("--- a/{}\n+++ b/{}\n", path, path).as_bytes());
+
+	// Difference in line numbering between the old and new file.
+	let mut skew: isize = 0;
+	let mut i = 0;
+	while i < changes.len() {
+		// Changes that share context become one hunk.
+		let mut j = i + 1;
+		while j < changes.len() && changes[j].first - changes[j - 1].last - 1 <= 2 * context {
+			j += 1;
+		}
+		let hunk = &changes[i .. j];
+		let first = hunk[0].first.saturating_sub(context);
+		let last = (hunk[hunk.len() - 1].last + context).min(numlines - 1);
+
+		let mut body = Vec::<u8>::new();
+		let mut newlen: usize = 0;
+		let mut line = first;
+		for change in hunk {
+			while line < change.first {
+				write_line(&mut body, b' ', lines.line(input, line));
+				newlen += 1;
+				line += 1;
+			}
+			while line <= change.last {
+				write_line(&mut body, b'-', lines.line(input, line));
+				line += 1;
+			}
+			for replacement_line in split_lines(&change.replacement) {
+				write_line(&mut body, b'+', replacement_line);
+				newlen += 1;
+			}
+		}
+		while line <= last {
+			write_line(&mut body, b' ', lines.line(input, line));
+			newlen += 1;
+			line += 1;
+		}
+		let oldlen = last + 1 - first;
+		let newfirst = (first as isize + skew) as usize;
+		out.extend_from_slice(format!(
+			"@@ -{} +{} @@\n", hunk_range(first, oldlen), hunk_range(newfirst, newlen)
+		).as_bytes());
+		out.extend_from_slice(&body);
+
+		skew += newlen as isize - oldlen as isize;
+		i = j;
+	}
+}
+
+fn hunk_range(first: usize, len: usize) -> String {
+	// An empty range is numbered by the line before it.
+	let start = if len == 0 { first } else { first + 1 };
+	if len == 1 {
+		format!("{}", start)
+	} else {
+		format!("{},{}", start, len)
+	}
+}
+
+fn split_lines(text: &[u8]) -> Vec<&[u8]> {
+	let mut split = Vec::new();
+	let mut begin = 0;
+	for (i, &c) in text.iter().enumerate() {
+		if c == b'\n' {
+			split.push(&text[begin ..= i]);
+			begin = i + 1;
+		}
+	}
+	if begin < text.len() {
+		split.push(&text[begin ..]);
+	}
+	split
+}
+
+fn write_line(out: &mut Vec<u8>, prefix: u8, line: &[u8]) {
+	out.push(prefix);
+	out.extend_from_slice(line);
+	if line.last() != Some(&b'\n') {
+		out.extend_from_slice(b"\n\\ No newline at end of file\n");
+	}
+}
+
+#[cfg(test)]
+use crate::rule::Rule;
+
+#[cfg(test)]
+fn patch_of(input: &[u8], context: usize) -> String {
+	let hardened = crate::harden(input, &crate::machine::Settings::transform()).unwrap();
+	let mut out = Vec::<u8>::new();
+	write_patch(&mut out, "x.sh", input, &hardened.edits, context);
+	String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_apply_edits() {
+	let edits = [
+		Edit { pos: 1, len: 0, replacement: b"\"", rule: Rule::Quote, group: 0 },
+		Edit { pos: 3, len: 1, replacement: b"", rule: Rule::Quote, group: 1 },
+	];
+	assert_eq!(apply_edits(b"a$b\"c", &edits, 0, 5), b"a\"$bc");
+	assert_eq!(apply_edits(b"a$b\"c", &edits, 2, 5), b"bc");
+	assert_eq!(apply_edits(b"a$b\"c", &edits, 0, 1), b"a");
+	assert_eq!(apply_edits(b"a$", &edits, 0, 1), b"a");
+	assert_eq!(apply_edits(b"a", &edits, 0, 1), b"a\"");
+}
+
+#[test]
+fn test_write_patch() {
+	assert_eq!(patch_of(b"a\nb\n", 3), "");
+	assert_eq!(patch_of(b"a\necho $a\nb\nc\nd\ne\nrm $f\n", 1), "\
+		--- a/x.sh\n+++ b/x.sh\n\
+		@@ -1,3 +1,3 @@\n a\n-echo $a\n+echo \"$a\"\n b\n\
+		@@ -6,2 +6,2 @@\n e\n-rm $f\n+rm \"$f\"\n\
+	");
+	assert_eq!(patch_of(b"echo $a\nb\nrm $f;", 1), "\
+		--- a/x.sh\n+++ b/x.sh\n\
+		@@ -1,3 +1,3 @@\n-echo $a\n+echo \"$a\"\n b\n\
+		-rm $f;\n\\ No newline at end of file\n\
+		+rm \"$f\";\n\\ No newline at end of file\n\
+	");
+}
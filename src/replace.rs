@@ -0,0 +1,264 @@
+/*
+ * Copyright 2024 Andreas Nordal
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+// Replacing a file without ever leaving it half-written: The new content goes to
+// a temporary file next to it, which gets the permissions, ownership and
+// extended attributes of the original where possible, is synced to disk,
+// and renamed over the original.
+//
+// Symlinks are followed: The file they point to is replaced, and they stay links.
+// Files with more than one hard link are not replaced, because the other names
+// would go on with the old content. Nor are read-only files, which could not be
+// written in place either.
+//
+// A backup, if wanted, is a copy of the original file under a name with a suffix
+// added, next to the file that is replaced. An existing backup is overwritten.
+// It is made from the original after the replacement, like the replacement
+// itself, so that the replaced file never has another link, even if interrupted.
+
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+pub fn replace(path: &Path, content: &[u8], backup: Option<&str>) -> io::Result<()> {
+	let target = fs::canonicalize(path)?;
+	let meta = fs::metadata(&target)?;
+	if !meta.is_file() {
+		return Err(io::Error::other("Not replaced, because it is not a regular file."));
+	}
+	if meta.permissions().readonly() {
+		return Err(io::Error::new(io::ErrorKind::PermissionDenied, "Not replaced, because it is read-only."));
+	}
+	if links(&meta) > 1 {
+		return Err(io::Error::other("Not replaced, because it has other hard links."));
+	}
+	// Still readable after the rename.
+	let original = match backup {
+		Some(_) => Some(fs::File::open(&target)?),
+		None => None,
+	};
+	let (tmp, file) = create_beside(&target, &meta)?;
+	let res = fill(file, content, &target, &meta)
+		.and_then(|()| fs::rename(&tmp, &target))
+	;
+	if res.is_err() {
+		let _ = fs::remove_file(&tmp);
+		return res;
+	}
+	if let (Some(mut original), Some(suffix)) = (original, backup) {
+		let mut name = target.as_os_str().to_owned();
+		name.push(suffix);
+		keep(&mut original, Path::new(&name), &target, &meta).map_err(|e| {
+			io::Error::new(e.kind(), format!("Replaced, but without backup: {}", e))
+		})?;
+	}
+	sync_dir(target.parent().unwrap())
+}
+
+// In the same directory, so that renaming it over the target is atomic.
+fn create_beside(target: &Path, meta: &fs::Metadata) -> io::Result<(PathBuf, fs::File)> {
+	static COUNTER: AtomicUsize = AtomicUsize::new(0);
+	let name = target.file_name().unwrap().to_string_lossy();
+	loop {
+		let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+		let tmp = target.with_file_name(format!(".{}.shellharden-{}-{}", name, std::process::id(), n));
+		let mut options = fs::OpenOptions::new();
+		options.write(true).create_new(true);
+		restrict(&mut options, meta);
+		match options.open(&tmp) {
+			Ok(file) => return Ok((tmp, file)),
+			Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+			Err(e) => return Err(e),
+		}
+	}
+}
+
+fn fill(mut file: fs::File, content: &[u8], target: &Path, meta: &fs::Metadata) -> io::Result<()> {
+	file.write_all(content)?;
+	copy_owner(&file, meta);
+	// After chown, which may clear setuid and setgid bits.
+	file.set_permissions(meta.permissions())?;
+	copy_xattrs(target, &file);
+	file.sync_all()
+}
+
+// Written the same way as the replacement.
+fn keep(original: &mut fs::File, name: &Path, target: &Path, meta: &fs::Metadata) -> io::Result<()> {
+	let (tmp, mut file) = create_beside(name, meta)?;
+	let res = io::copy(original, &mut file)
+		.and_then(|_| fill(file, b"", target, meta))
+		.and_then(|()| fs::rename(&tmp, name))
+	;
+	if res.is_err() {
+		let _ = fs::remove_file(&tmp);
+	}
+	res
+}
+
+#[cfg(unix)]
+fn links(meta: &fs::Metadata) -> u64 {
+	std::os::unix::fs::MetadataExt::nlink(meta)
+}
+
+#[cfg(not(unix))]
+fn links(_: &fs::Metadata) -> u64 {
+	1
+}
+
+// Not readable by more than the original, even while being written.
+#[cfg(unix)]
+fn restrict(options: &mut fs::OpenOptions, meta: &fs::Metadata) {
+	use std::os::unix::fs::MetadataExt;
+	use std::os::unix::fs::OpenOptionsExt;
+	options.mode(meta.mode() & 0o777);
+}
+
+#[cfg(not(unix))]
+fn restrict(_: &mut fs::OpenOptions, _: &fs::Metadata) {}
+
+// Only the owner or root may change the owner, so this is best effort.
+#[cfg(unix)]
+fn copy_owner(file: &fs::File, meta: &fs::Metadata) {
+	use std::os::unix::fs::MetadataExt;
+	if std::os::unix::fs::fchown(file, Some(meta.uid()), Some(meta.gid())).is_err() {
+		let _ = std::os::unix::fs::fchown(file, None, Some(meta.gid()));
+	}
+}
+
+#[cfg(not(unix))]
+fn copy_owner(_: &fs::File, _: &fs::Metadata) {}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+	fs::File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_: &Path) -> io::Result<()> {
+	Ok(())
+}
+
+// Best effort: Some attributes, like security labels, may need privileges to set.
+#[cfg(target_os = "linux")]
+fn copy_xattrs(from: &Path, to: &fs::File) {
+	use std::ffi::CString;
+	use std::os::raw::c_char;
+	use std::os::raw::c_int;
+	use std::os::raw::c_void;
+	use std::os::unix::ffi::OsStrExt;
+	use std::os::unix::io::AsRawFd;
+
+	extern "C" {
+		fn listxattr(path: *const c_char, list: *mut c_char, size: usize) -> isize;
+		fn getxattr(path: *const c_char, name: *const c_char, value: *mut c_void, size: usize) -> isize;
+		fn fsetxattr(fd: c_int, name: *const c_char, value: *const c_void, size: usize, flags: c_int) -> c_int;
+	}
+
+	let path = match CString::new(from.as_os_str().as_bytes()) {
+		Ok(path) => path,
+		Err(_) => return,
+	};
+	let names = match read_sized(|buf, size| unsafe { listxattr(path.as_ptr(), buf as *mut c_char, size) }) {
+		Some(names) => names,
+		None => return,
+	};
+	for name in names.split(|&c| c == 0).filter(|name| !name.is_empty()) {
+		let name = CString::new(name).unwrap();
+		let value = read_sized(|buf, size| unsafe {
+			getxattr(path.as_ptr(), name.as_ptr(), buf as *mut c_void, size)
+		});
+		if let Some(value) = value {
+			unsafe {
+				fsetxattr(to.as_raw_fd(), name.as_ptr(), value.as_ptr() as *const c_void, value.len(), 0);
+			}
+		}
+	}
+}
+
+#[cfg(not(target_os = "linux"))]
+fn copy_xattrs(_: &Path, _: &fs::File) {}
+
+// Calls the function first to get the size, then to fill a buffer of that size.
+#[cfg(target_os = "linux")]
+fn read_sized<F: Fn(*mut u8, usize) -> isize>(call: F) -> Option<Vec<u8>> {
+	let size = call(std::ptr::null_mut(), 0);
+	if size <= 0 {
+		return None;
+	}
+	let mut buf = vec![0u8; size as usize];
+	let size = call(buf.as_mut_ptr(), buf.len());
+	if size < 0 {
+		return None;
+	}
+	buf.truncate(size as usize);
+	Some(buf)
+}
+
+#[test]
+fn test_replace() {
+	let dir = std::env::temp_dir().join(format!("shellharden-test-replace-{}", std::process::id()));
+	let _ = fs::remove_dir_all(&dir);
+	fs::create_dir(&dir).unwrap();
+	let file = dir.join("a.sh");
+	fs::write(&file, b"echo $a\n").unwrap();
+	let permissions = fs::metadata(&file).unwrap().permissions();
+	let mut readonly = permissions.clone();
+	readonly.set_readonly(true);
+	fs::set_permissions(&file, readonly).unwrap();
+	assert_eq!(replace(&file, b"x\n", None).unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+	assert_eq!(fs::read(&file).unwrap(), b"echo $a\n");
+
+	fs::set_permissions(&file, permissions.clone()).unwrap();
+	replace(&file, b"echo \"$a\"\n", None).unwrap();
+	assert_eq!(fs::read(&file).unwrap(), b"echo \"$a\"\n");
+	assert_eq!(fs::metadata(&file).unwrap().permissions(), permissions);
+	assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+	#[cfg(unix)]
+	{
+		let link = dir.join("link.sh");
+		std::os::unix::fs::symlink("a.sh", &link).unwrap();
+		replace(&link, b"x\n", None).unwrap();
+		assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+		assert_eq!(fs::read(&file).unwrap(), b"x\n");
+
+		let hardlink = dir.join("hard.sh");
+		fs::hard_link(&file, &hardlink).unwrap();
+		assert!(replace(&file, b"y\n", None).is_err());
+		assert_eq!(fs::read(&file).unwrap(), b"x\n");
+		assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+	}
+	fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_replace_backup() {
+	let dir = std::env::temp_dir().join(format!("shellharden-test-backup-{}", std::process::id()));
+	let _ = fs::remove_dir_all(&dir);
+	fs::create_dir(&dir).unwrap();
+	let file = dir.join("a.sh");
+	let backup = dir.join("a.sh.orig");
+	fs::write(&file, b"1\n").unwrap();
+	fs::write(&backup, b"0\n").unwrap();
+
+	replace(&file, b"2\n", Some(".orig")).unwrap();
+	assert_eq!(fs::read(&file).unwrap(), b"2\n");
+	assert_eq!(fs::read(&backup).unwrap(), b"1\n");
+
+	// The backup is not another link to the file, so it can be replaced again.
+	replace(&file, b"3\n", Some(".orig")).unwrap();
+	assert_eq!(fs::read(&backup).unwrap(), b"2\n");
+	assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+	#[cfg(unix)]
+	assert_eq!(links(&fs::metadata(&file).unwrap()), 1);
+	fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,315 @@
+/*
+ * Copyright 2024 Andreas Nordal
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+
+use crate::errfmt::ContextualError;
+use crate::filestream::os_bytes;
+use crate::filestream::slurp;
+use crate::json;
+use crate::machine::Edit;
+use crate::machine::Error;
+use crate::machine::OutputSelector;
+use crate::machine::Settings;
+use crate::patch;
+use crate::sarif;
+
+// A suggested change or syntax error, located for humans and tools alike.
+// A change consists of the edits that only make sense together, like a pair of quotes.
+pub struct Finding<'a> {
+	pub region: Region,
+	pub original: &'a [u8],
+	// What the original becomes with the change. None for syntax errors.
+	pub replacement: Option<Vec<u8>>,
+	// Each edit of the change, by where and what to put there instead.
+	pub edits: Vec<(Region, &'static [u8])>,
+	pub rule: &'static str,
+	pub message: &'static str,
+}
+
+pub struct Region {
+	pub pos: usize,
+	pub len: usize,
+	pub line: usize,
+	pub col: usize,
+	pub end_line: usize,
+	pub end_col: usize,
+}
+
+pub struct Lines {
+	starts: Vec<usize>,
+	len: usize,
+}
+
+impl Lines {
+	pub fn new(input: &[u8]) -> Lines {
+		let mut starts = vec![0];
+		for (i, &c) in input.iter().enumerate() {
+			if c == b'\n' {
+				starts.push(i + 1);
+			}
+		}
+		Lines{starts, len: input.len()}
+	}
+	// Not counting the empty string after a final newline.
+	pub fn count(&self) -> usize {
+		if self.starts[self.starts.len() - 1] == self.len {
+			self.starts.len() - 1
+		} else {
+			self.starts.len()
+		}
+	}
+	pub fn start_of(&self, line: usize) -> usize {
+		if line < self.starts.len() {
+			self.starts[line]
+		} else {
+			self.len
+		}
+	}
+	pub fn line<'a>(&self, input: &'a [u8], line: usize) -> &'a [u8] {
+		&input[self.start_of(line) .. self.start_of(line + 1)]
+	}
+	pub fn line_start(&self, pos: usize) -> (usize, usize) {
+		let line = match self.starts.binary_search(&pos) {
+			Ok(i) => i,
+			Err(i) => i - 1,
+		};
+		(line, self.starts[line])
+	}
+	pub fn region(&self, input: &[u8], begin: usize, end: usize) -> Region {
+		let (line, col) = self.linecol(input, begin);
+		let (end_line, end_col) = self.linecol(input, end);
+		Region{pos: begin, len: end - begin, line, col, end_line, end_col}
+	}
+	// 1-based line and column, where the column counts codepoints.
+	pub fn linecol(&self, input: &[u8], pos: usize) -> (usize, usize) {
+		let (line, begin) = self.line_start(pos);
+		let end = pos.min(input.len());
+		let col = input[begin.min(end) .. end].iter().filter(|&&c| !is_utf8_continuation(c)).count();
+		(line + 1, col + 1)
+	}
+}
+
+fn is_utf8_continuation(c: u8) -> bool {
+	c >> 6 == 0b10
+}
+
+pub fn findings<'a>(
+	input: &'a [u8],
+	edits: &[Edit],
+	errors: &[ContextualError],
+) -> Vec<Finding<'a>> {
+	let lines = Lines::new(input);
+	let groups = groups(edits);
+	let mut found = Vec::with_capacity(groups.len() + errors.len());
+	for group in groups {
+		let (begin, end) = extent(&group);
+		let mut replacement = Vec::new();
+		let mut pos = begin;
+		for edit in &group {
+			replacement.extend_from_slice(&input[pos .. edit.pos]);
+			replacement.extend_from_slice(edit.replacement);
+			pos = edit.pos + edit.len;
+		}
+		replacement.extend_from_slice(&input[pos .. end]);
+		found.push(Finding {
+			region: lines.region(input, begin, end),
+			original: &input[begin .. end],
+			replacement: Some(replacement),
+			edits: group.iter().map(|edit| {
+				(lines.region(input, edit.pos, edit.pos + edit.len), edit.replacement)
+			}).collect(),
+			rule: group[0].rule.id(),
+			message: group[0].rule.summary(),
+		});
+	}
+	for e in errors {
+		let begin = e.offset.min(input.len());
+		let end = (e.offset + e.len).min(input.len());
+		found.push(Finding {
+			region: lines.region(input, begin, end),
+			original: &input[begin .. end],
+			replacement: None,
+			edits: Vec::new(),
+			rule: e.id,
+			message: e.typ,
+		});
+	}
+	found.sort_by_key(|finding| finding.region.pos);
+	found
+}
+
+// Edits that belong together, in order of appearance.
+pub fn groups(edits: &[Edit]) -> Vec<Vec<&Edit>> {
+	let mut groups: Vec<Vec<&Edit>> = Vec::new();
+	let mut index: HashMap<usize, usize> = HashMap::new();
+	for edit in edits {
+		let i = *index.entry(edit.group).or_insert_with(|| {
+			groups.push(Vec::new());
+			groups.len() - 1
+		});
+		groups[i].push(edit);
+	}
+	groups
+}
+
+pub fn extent(group: &[&Edit]) -> (usize, usize) {
+	let begin = group.iter().map(|e| e.pos).min().unwrap();
+	let end = group.iter().map(|e| e.pos + e.len).max().unwrap();
+	(begin, end)
+}
+
+// Like machine::treatfile, but for the output formats that describe the changes
+// instead of making them. These need to see the whole file.
+// The output is appended to `out`, which may hold previous files' output.
+pub fn treatfile(
+	path: &std::ffi::OsString,
+	sett: &Settings,
+	out: &mut Vec<u8>,
+	warnings: &mut Vec<ContextualError>,
+) -> (bool, Result<(), Error>) {
+	match slurp(path) {
+		Ok(input) => treatstring(path, &input, sett, out, warnings),
+		Err(e) => (false, Err(Error::Stdio(e))),
+	}
+}
+
+// Like treatfile, but for a script that is already in memory.
+pub fn treatstring(
+	path: &OsStr,
+	input: &[u8],
+	sett: &Settings,
+	out: &mut Vec<u8>,
+	warnings: &mut Vec<ContextualError>,
+) -> (bool, Result<(), Error>) {
+	let machine_sett = Settings {
+		osel: OutputSelector::Transform,
+		syntax: false,
+		..sett.clone()
+	};
+	let hardened = match crate::harden(input, &machine_sett) {
+		Ok(hardened) => hardened,
+		Err(e) => return (false, Err(Error::Stdio(e))),
+	};
+	warnings.extend(hardened.warnings);
+	let (edits, errors) = (hardened.edits, hardened.errors);
+	let found = findings(input, &edits, &errors);
+	let name: &str = &path.to_string_lossy();
+
+	for finding in &found {
+		match sett.osel {
+			OutputSelector::Json => json::write_finding(out, name, finding),
+			OutputSelector::Sarif => sarif::write_result(out, name, finding),
+			OutputSelector::Gcc => write_gcc(out, name, finding),
+			_ => {}
+		}
+	}
+	match sett.osel {
+		OutputSelector::Patch => patch::write_patch(out, name, input, &edits, sett.patch_context),
+		// Despite syntax errors elsewhere in the file.
+		OutputSelector::List if !edits.is_empty() => {
+			out.extend_from_slice(&os_bytes(path));
+			out.push(b'\n');
+		}
+		OutputSelector::List0 if !edits.is_empty() => {
+			out.extend_from_slice(&os_bytes(path));
+			out.push(b'\0');
+		}
+		_ => {}
+	}
+
+	let change = !edits.is_empty();
+	if !errors.is_empty() {
+		(change, Err(Error::Syntax(errors)))
+	} else if change {
+		(change, Err(Error::Check))
+	} else {
+		(change, Ok(()))
+	}
+}
+
+// The compiler style that editors and CI log parsers understand.
+pub fn write_gcc(out: &mut Vec<u8>, path: &str, finding: &Finding) {
+	let severity = if finding.replacement.is_some() { "warning" } else { "error" };
+	out.extend_from_slice(format!(
+		"{}:{}:{}: {}: {} [{}]\n",
+		path, finding.region.line, finding.region.col, severity, finding.message, finding.rule,
+	).as_bytes());
+}
+
+#[test]
+fn test_linecol() {
+	let input = "ab\n\næøå $x\n".as_bytes();
+	let lines = Lines::new(input);
+	assert_eq!(lines.linecol(input, 0), (1, 1));
+	assert_eq!(lines.linecol(input, 2), (1, 3));
+	assert_eq!(lines.linecol(input, 3), (2, 1));
+	assert_eq!(lines.linecol(input, 4), (3, 1));
+	assert_eq!(lines.linecol(input, 11), (3, 5));
+	assert_eq!(lines.linecol(input, input.len()), (4, 1));
+}
+
+#[test]
+fn test_findings() {
+	let input = b"echo $a `pwd`\n";
+	let found = findings(input, &crate::harden(input, &Settings::transform()).unwrap().edits, &[]);
+	let changes: Vec<(usize, &[u8], &[u8], usize)> = found.iter().map(|f| {
+		(f.region.pos, f.original, f.replacement.as_deref().unwrap(), f.edits.len())
+	}).collect();
+	assert_eq!(changes, vec![
+		(5, &b"$a"[..], &b"\"$a\""[..], 2),
+		(8, &b"`pwd`"[..], &b"\"`pwd`\""[..], 2),
+		(8, &b"`pwd`"[..], &b"$PWD"[..], 1),
+	]);
+	assert_eq!((found[0].edits[1].0.pos, found[0].edits[1].0.col), (7, 8));
+}
+
+#[test]
+fn test_write_gcc() {
+	let input = b"x\necho $10\n";
+	let e = crate::harden(input, &Settings::transform()).unwrap().errors;
+	let mut out = Vec::<u8>::new();
+	for finding in &findings(input, &[], &e) {
+		write_gcc(&mut out, "x.sh", finding);
+	}
+	assert_eq!(out, &b"x.sh:2:6: error: Unsupported syntax: Syntactic pitfall [doubledigit]\n"[..]);
+
+	// Not one line for each quote.
+	let input = b"echo $a\n";
+	let edits = crate::harden(input, &Settings::transform()).unwrap().edits;
+	let mut out = Vec::<u8>::new();
+	for finding in &findings(input, &edits, &[]) {
+		write_gcc(&mut out, "x.sh", finding);
+	}
+	assert_eq!(out, &b"x.sh:1:6: warning: Quote to prevent word splitting and pathname expansion [quote-var]\n"[..]);
+}
+
+#[test]
+fn test_list() {
+	let sett = Settings {
+		osel: OutputSelector::List0,
+		..Settings::default()
+	};
+	let mut out = Vec::<u8>::new();
+	let mut treat = |name: &str, input: &[u8]| treatstring(OsStr::new(name), input, &sett, &mut out, &mut Vec::new());
+	assert!(matches!(treat("a.sh", b"echo $a\n"), (true, Err(Error::Check))));
+	assert!(matches!(treat("b.sh", b"echo \"$b\"\n"), (false, Ok(()))));
+	assert!(matches!(treat("c.sh", b"echo $10\n"), (false, Err(Error::Syntax(_)))));
+	assert!(matches!(treat("d.sh", b"echo $10 $d\n"), (true, Err(Error::Syntax(_)))));
+	assert_eq!(out, b"a.sh\0d.sh\0");
+
+	#[cfg(unix)]
+	{
+		use std::os::unix::ffi::OsStrExt;
+		let mut out = Vec::<u8>::new();
+		let name = OsStr::from_bytes(b"\xff.sh");
+		assert!(treatstring(name, b"echo $a\n", &sett, &mut out, &mut Vec::new()).1.is_err());
+		assert_eq!(out, b"\xff.sh\0");
+	}
+}
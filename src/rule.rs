@@ -0,0 +1,116 @@
+/*
+ * Copyright 2024 Andreas Nordal
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+// The reason behind a suggested change.
+// Every replacement made by a situation is tagged with one of these.
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Debug)]
+pub enum Rule {
+	Quote,
+	DollarStar,
+	Backtick,
+	Pwd,
+	VarBrace,
+	ArrayStar,
+	ForInArray,
+	TestEmptiness,
+	TestXyes,
+	PrematureEsac,
+	PositionalBraces,
+	TestNUnquoted,
+	AddVarBraces,
+}
+
+pub const ALL: [Rule; 13] = [
+	Rule::Quote,
+	Rule::DollarStar,
+	Rule::Backtick,
+	Rule::Pwd,
+	Rule::VarBrace,
+	Rule::ArrayStar,
+	Rule::ForInArray,
+	Rule::TestEmptiness,
+	Rule::TestXyes,
+	Rule::PrematureEsac,
+	Rule::PositionalBraces,
+	Rule::TestNUnquoted,
+	Rule::AddVarBraces,
+];
+
+// Syntax errors are not rules one can turn off, but they have ids too:
+// (id, short description)
+pub const SYNTAX_ERRORS: [(&str, &str); 2] = [
+	("doubledigit", "Unsupported syntax: Syntactic pitfall"),
+	("unexpected-eof", "Unexpected end of file"),
+];
+
+// The rules named by a rule id, or "all".
+pub fn select(id: &str) -> Option<Vec<Rule>> {
+	if id == "all" {
+		return Some(ALL.to_vec());
+	}
+	ALL.iter().find(|rule| rule.id() == id).map(|&rule| vec![rule])
+}
+
+impl Rule {
+	pub fn id(self) -> &'static str {
+		match self {
+			Rule::Quote => "quote-var",
+			Rule::DollarStar => "dollar-star",
+			Rule::Backtick => "backtick",
+			Rule::Pwd => "pwd",
+			Rule::VarBrace => "varbrace",
+			Rule::ArrayStar => "array-star",
+			Rule::ForInArray => "for-in-array",
+			Rule::TestEmptiness => "test-emptiness",
+			Rule::TestXyes => "test-xyes",
+			Rule::PrematureEsac => "premature-esac",
+			Rule::PositionalBraces => "positional-braces",
+			Rule::TestNUnquoted => "test-n-unquoted",
+			Rule::AddVarBraces => "add-varbraces",
+		}
+	}
+	pub fn summary(self) -> &'static str {
+		match self {
+			Rule::Quote => "Quote to prevent word splitting and pathname expansion",
+			Rule::DollarStar => "Pass the arguments on as they were with \"$@\"",
+			Rule::Backtick => "Use $( ), which nests, instead of backticks",
+			Rule::Pwd => "Use $PWD instead of running pwd",
+			Rule::VarBrace => "Remove unnecessary braces",
+			Rule::ArrayStar => "Expand the array instead of serializing it",
+			Rule::ForInArray => "Loop over the array instead of the word-split string",
+			Rule::TestEmptiness => "Compare with the empty string instead of using -n or -z",
+			Rule::TestXyes => "Remove the unnecessary x-prefix from the comparison",
+			Rule::PrematureEsac => "Terminate the last case arm before esac",
+			Rule::PositionalBraces => "Use braces for positional parameters above 9, as intended",
+			Rule::TestNUnquoted => "Quote the operand of -n, which is otherwise always true",
+			Rule::AddVarBraces => "Use braces around variable names",
+		}
+	}
+	// Whether enabled unless disabled. Otherwise, it must be enabled.
+	pub fn is_default(self) -> bool {
+		self != Rule::AddVarBraces
+	}
+	// Whether the script would do something else, because what it did never worked.
+	pub fn changes_behavior(self) -> bool {
+		matches!(self, Rule::PositionalBraces | Rule::TestNUnquoted)
+	}
+}
+
+#[test]
+fn test_select() {
+	assert_eq!(select("test-xyes"), Some(vec![Rule::TestXyes]));
+	assert_eq!(select("all").map(|rules| rules.len()), Some(ALL.len()));
+	assert_eq!(select("xyes"), None);
+	for rule in ALL {
+		assert_eq!(select(rule.id()), Some(vec![rule]));
+	}
+}
@@ -0,0 +1,144 @@
+/*
+ * Copyright 2024 Andreas Nordal
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+// Static Analysis Results Interchange Format, version 2.1.0.
+// All files go into one log with one run, so results are collected first.
+
+use crate::json::write_str;
+use crate::json::write_uint;
+use crate::report::Finding;
+use crate::report::Region;
+use crate::rule;
+
+pub fn write_result(out: &mut Vec<u8>, path: &str, finding: &Finding) {
+	if !out.is_empty() {
+		out.extend_from_slice(b",\n");
+	}
+	let level: &[u8] = if finding.replacement.is_some() { b"warning" } else { b"error" };
+	out.extend_from_slice(b"{\"ruleId\":");
+	write_str(out, finding.rule.as_bytes());
+	out.extend_from_slice(b",\"level\":");
+	write_str(out, level);
+	out.extend_from_slice(b",\"message\":{\"text\":");
+	write_str(out, finding.message.as_bytes());
+	out.extend_from_slice(b"},\"locations\":[{\"physicalLocation\":{\"artifactLocation\":");
+	write_artifact(out, path);
+	out.extend_from_slice(b",\"region\":");
+	write_region(out, &finding.region);
+	out.extend_from_slice(b"}}]");
+	// All edits of a change in one fix, which breaks the script if only partly applied.
+	if finding.replacement.is_some() {
+		out.extend_from_slice(b",\"fixes\":[{\"description\":{\"text\":");
+		write_str(out, finding.message.as_bytes());
+		out.extend_from_slice(b"},\"artifactChanges\":[{\"artifactLocation\":");
+		write_artifact(out, path);
+		out.extend_from_slice(b",\"replacements\":[");
+		for (i, (region, replacement)) in finding.edits.iter().enumerate() {
+			if i != 0 {
+				out.push(b',');
+			}
+			out.extend_from_slice(b"{\"deletedRegion\":");
+			write_region(out, region);
+			out.extend_from_slice(b",\"insertedContent\":{\"text\":");
+			write_str(out, replacement);
+			out.extend_from_slice(b"}}");
+		}
+		out.extend_from_slice(b"]}]}]");
+	}
+	out.extend_from_slice(b"}");
+}
+
+pub fn write_log(out: &mut Vec<u8>, results: &[u8]) {
+	out.extend_from_slice(b"{\"version\":\"2.1.0\",\
+		\"$schema\":\"https://json.schemastore.org/sarif-2.1.0.json\",\
+		\"runs\":[{\"tool\":{\"driver\":{\"name\":\"shellharden\",\"version\":");
+	write_str(out, env!("CARGO_PKG_VERSION").as_bytes());
+	out.extend_from_slice(b",\"informationUri\":");
+	write_str(out, env!("CARGO_PKG_REPOSITORY").as_bytes());
+	out.extend_from_slice(b",\"rules\":[\n");
+	let mut first = true;
+	for rule in rule::ALL.iter() {
+		write_rule_descriptor(out, &mut first, rule.id(), rule.summary(), b"warning");
+	}
+	for (id, typ) in rule::SYNTAX_ERRORS.iter() {
+		write_rule_descriptor(out, &mut first, id, typ, b"error");
+	}
+	out.extend_from_slice(b"\n]}},\"columnKind\":\"unicodeCodePoints\",\"results\":[\n");
+	out.extend_from_slice(results);
+	out.extend_from_slice(b"\n]}]}\n");
+}
+
+fn write_rule_descriptor(out: &mut Vec<u8>, first: &mut bool, id: &str, summary: &str, level: &[u8]) {
+	if !*first {
+		out.extend_from_slice(b",\n");
+	}
+	*first = false;
+	out.extend_from_slice(b"{\"id\":");
+	write_str(out, id.as_bytes());
+	out.extend_from_slice(b",\"shortDescription\":{\"text\":");
+	write_str(out, summary.as_bytes());
+	out.extend_from_slice(b"},\"defaultConfiguration\":{\"level\":");
+	write_str(out, level);
+	out.extend_from_slice(b"}}");
+}
+
+fn write_artifact(out: &mut Vec<u8>, path: &str) {
+	out.extend_from_slice(b"{\"uri\":");
+	write_str(out, uri_escape(path).as_bytes());
+	out.extend_from_slice(b"}");
+}
+
+fn write_region(out: &mut Vec<u8>, region: &Region) {
+	out.extend_from_slice(b"{\"startLine\":");
+	write_uint(out, region.line);
+	out.extend_from_slice(b",\"startColumn\":");
+	write_uint(out, region.col);
+	out.extend_from_slice(b",\"endLine\":");
+	write_uint(out, region.end_line);
+	out.extend_from_slice(b",\"endColumn\":");
+	write_uint(out, region.end_col);
+	out.extend_from_slice(b",\"byteOffset\":");
+	write_uint(out, region.pos);
+	out.extend_from_slice(b",\"byteLength\":");
+	write_uint(out, region.len);
+	out.extend_from_slice(b"}");
+}
+
+// A relative path is a valid URI reference, as long as it is percent-encoded.
+fn uri_escape(path: &str) -> String {
+	let mut uri = String::with_capacity(path.len());
+	for &c in path.as_bytes() {
+		match c {
+			b'a' ..= b'z' | b'A' ..= b'Z' | b'0' ..= b'9' |
+			b'-' | b'.' | b'_' | b'~' | b'/' => uri.push(char::from(c)),
+			_ => uri.push_str(&format!("%{:02X}", c)),
+		}
+	}
+	uri
+}
+
+#[test]
+fn test_uri_escape() {
+	assert_eq!(uri_escape("dir/file.sh"), "dir/file.sh");
+	assert_eq!(uri_escape("my dir/æ.sh"), "my%20dir/%C3%A6.sh");
+}
+
+#[test]
+fn test_write_result() {
+	let input = b"echo $a\n";
+	let edits = crate::harden(input, &crate::machine::Settings::transform()).unwrap().edits;
+	let mut out = Vec::<u8>::new();
+	for finding in &crate::report::findings(input, &edits, &[]) {
+		write_result(&mut out, "x.sh", finding);
+	}
+	let out = String::from_utf8(out).unwrap();
+	assert_eq!(out.matches("\"ruleId\"").count(), 1);
+	assert_eq!(out.matches("\"fixes\"").count(), 1);
+	assert_eq!(out.matches("\"deletedRegion\"").count(), 2);
+	assert!(out.contains("\"region\":{\"startLine\":1,\"startColumn\":6,\"endLine\":1,\"endColumn\":8,"));
+}
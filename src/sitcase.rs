@@ -11,12 +11,15 @@ use crate::situation::Situation;
 use crate::situation::Transition;
 use crate::sitextent::SitExtent;
 use crate::situation::WhatNow;
+use crate::situation::alt;
 use crate::situation::flush;
 use crate::situation::pop;
 use crate::situation::push;
 use crate::situation::COLOR_NORMAL;
 use crate::situation::COLOR_KWD;
 
+use crate::rule::Rule;
+
 use crate::microparsers::predlen;
 use crate::microparsers::is_lowercase;
 use crate::microparsers::is_whitespace;
@@ -105,7 +108,7 @@ impl Situation for SitCaseArm {
 			if i + len != horizon.input.len() || (i == 0 && !horizon.is_lengthenable) {
 				let word = &horizon.input[i..i+len];
 				if word == b"esac" {
-					return pop(i, 0, Some(b";; "));
+					return pop(i, 0, Some(alt(Rule::PrematureEsac, b";; ")));
 				}
 			}
 			return keyword_or_command(0x100, horizon, i);
@@ -127,7 +130,7 @@ fn become_case_in(pre: usize) -> WhatNow {
 fn pop_kw(pre: usize, len: usize) -> WhatNow {
 	WhatNow {
 		transform: (pre, len, None),
-		transition: Transition::Replace(Box::new(SitExtent { len: 0, color: COLOR_KWD })),
+		transition: Transition::Replace(Box::new(SitExtent { len: 0, color: COLOR_KWD, end_insert: None })),
 	}
 }
 
@@ -171,7 +174,7 @@ fn test_sit_casein() {
 #[test]
 fn test_sit_casearm() {
 	let found_command = push((0, 0, None), Box::new(SitCmd{end_trigger: 0x100}));
-	let found_the_esac_word = pop(0, 0, Some(b";; "));
+	let found_the_esac_word = pop(0, 0, Some(alt(Rule::PrematureEsac, b";; ")));
 
 	sit_expect!(SitCaseArm{}, b"", &flush(0));
 	sit_expect!(SitCaseArm{}, b" ", &flush(1));
@@ -6,6 +6,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/.
  */
 
+use crate::situation::Alt;
 use crate::situation::Horizon;
 use crate::situation::Situation;
 use crate::situation::Transition;
@@ -24,7 +25,7 @@ use crate::commonargcmd::common_cmd;
 
 pub struct SitNormal {
 	pub end_trigger :u16,
-	pub end_replace :Option<&'static [u8]>,
+	pub end_replace :Option<Alt>,
 }
 
 impl Situation for SitNormal {
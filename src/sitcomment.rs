@@ -1,30 +1,122 @@
 /*
- * Copyright 2018 - 2019 Andreas Nordal
+ * Copyright 2018 - 2024 Andreas Nordal
  *
  * This Source Code Form is subject to the terms of the Mozilla Public
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/.
  */
 
+use crate::rule;
+use crate::situation::Directive;
 use crate::situation::Horizon;
+use crate::situation::Problem;
 use crate::situation::Situation;
 use crate::situation::WhatNow;
+use crate::situation::flush;
 use crate::situation::flush_or_pop;
 use crate::situation::COLOR_CMT;
 use crate::situation::pop;
 
-pub struct SitComment {}
+use crate::microparsers::is_whitespace;
+
+pub struct SitComment {
+	// Until the beginning of the comment is seen.
+	pub directive: Option<Directive>,
+	pub len: usize,
+}
 
 impl Situation for SitComment {
 	fn whatnow(&mut self, horizon: Horizon) -> WhatNow {
-		for (i, &a) in horizon.input.iter().enumerate() {
-			if a == b'\n' {
-				return pop(i, 0, None);
+		let end = horizon.input.iter().position(|&a| a == b'\n');
+		if self.directive.is_none() {
+			if end.is_none() && horizon.is_lengthenable {
+				return flush(0);
 			}
+			self.directive = Some(parse_directive(&horizon.input[.. end.unwrap_or(horizon.input.len())]));
+		}
+		let len = end.unwrap_or(horizon.input.len());
+		self.len += len;
+		match end {
+			Some(_) => pop(len, 0, None),
+			None => flush_or_pop(len),
 		}
-		flush_or_pop(horizon.input.len())
 	}
 	fn get_color(&self) -> u32 {
 		COLOR_CMT
 	}
+	fn directive(&mut self) -> Option<Directive> {
+		let mut directive = self.directive.take().unwrap_or_default();
+		directive.len = self.len;
+		Some(directive)
+	}
+}
+
+// The comment text after #, like " shellharden disable=quote-var,pwd disable-file=backtick".
+// Anything after the directives is ignored. Unknown rule ids are problems,
+// and so is a directive that disables nothing.
+fn parse_directive(text: &[u8]) -> Directive {
+	let mut directive = Directive::default();
+	let mut offset = 0;
+	let mut words = text.split(|&c| is_whitespace(c)).map(|word| {
+		offset += word.len() + 1;
+		(offset - word.len() - 1, word)
+	}).filter(|(_, word)| !word.is_empty());
+	let begin = match words.next() {
+		Some((begin, b"shellharden")) => begin,
+		_ => return directive,
+	};
+	for (offset, word) in words {
+		let (rules, ids) = if let Some(ids) = word.strip_prefix(b"disable=") {
+			(&mut directive.next, ids)
+		} else if let Some(ids) = word.strip_prefix(b"disable-file=") {
+			(&mut directive.file, ids)
+		} else {
+			break;
+		};
+		let mut id_offset = offset + word.len() - ids.len();
+		for id in ids.split(|&c| c == b',') {
+			match rule::select(&String::from_utf8_lossy(id)) {
+				Some(selected) => rules.extend(selected),
+				None if !id.is_empty() => directive.problems.push((id_offset, id.len(), Problem::UnknownRule)),
+				None => {}
+			}
+			id_offset += id.len() + 1;
+		}
+	}
+	if directive.next.is_empty() && directive.file.is_empty() && directive.problems.is_empty() {
+		let end = text.iter().rposition(|&c| !is_whitespace(c)).map_or(begin, |i| i + 1);
+		directive.problems.push((begin, end - begin, Problem::NoEffect));
+	}
+	directive
+}
+
+#[cfg(test)]
+use crate::testhelpers::*;
+#[cfg(test)]
+use crate::rule::Rule;
+
+#[test]
+fn test_sit_comment() {
+	let subj = || SitComment { directive: None, len: 0 };
+
+	sit_expect!(subj(), b" x", &flush(0), &flush_or_pop(2));
+	sit_expect!(subj(), b" x\n", &pop(2, 0, None));
+
+	let mut sit = subj();
+	sit.whatnow(Horizon { input: b" shellharden disable=pwd", is_lengthenable: false, is_eof: true });
+	assert_eq!(sit.directive(), Some(Directive { next: vec![Rule::Pwd], len: 24, ..Directive::default() }));
+}
+
+#[test]
+fn test_parse_directive() {
+	assert_eq!(parse_directive(b" shellcheck disable=SC2086"), Directive::default());
+	assert_eq!(parse_directive(b"shellharden\tdisable=quote-var,nope disable-file=backtick -- why"), Directive {
+		next: vec![Rule::Quote],
+		file: vec![Rule::Backtick],
+		problems: vec![(30, 4, Problem::UnknownRule)],
+		..Directive::default()
+	});
+	assert_eq!(parse_directive(b" shellharden disable=all").next, rule::ALL.to_vec());
+	assert_eq!(parse_directive(b" shellharden disable= ").problems, vec![(1, 20, Problem::NoEffect)]);
+	assert_eq!(parse_directive(b" shellharden disabel=pwd ").problems, vec![(1, 23, Problem::NoEffect)]);
 }
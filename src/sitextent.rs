@@ -6,6 +6,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/.
  */
 
+use crate::situation::Alt;
 use crate::situation::Horizon;
 use crate::situation::Situation;
 use crate::situation::WhatNow;
@@ -16,12 +17,13 @@ use crate::situation::push;
 pub struct SitExtent{
 	pub len: usize,
 	pub color: u32,
+	pub end_insert: Option<Alt>,
 }
 
 impl Situation for SitExtent {
 	fn whatnow(&mut self, horizon: Horizon) -> WhatNow {
 		if horizon.input.len() >= self.len {
-			return pop(self.len, 0, None);
+			return pop(self.len, 0, self.end_insert);
 		}
 		self.len -= horizon.input.len();
 		flush(horizon.input.len())
@@ -32,9 +34,9 @@ impl Situation for SitExtent {
 }
 
 pub fn push_extent(color: u32, pre: usize, len: usize) -> WhatNow {
-	push((pre, 0, None), Box::new(SitExtent { len, color }))
+	push((pre, 0, None), Box::new(SitExtent { len, color, end_insert: None }))
 }
 
-pub fn push_replaceable(color: u32, pre: usize, len: usize, alt: Option<&'static [u8]>) -> WhatNow {
-	push((pre, len, alt), Box::new(SitExtent { len: 0, color }))
+pub fn push_replaceable(color: u32, pre: usize, len: usize, alt: Option<Alt>) -> WhatNow {
+	push((pre, len, alt), Box::new(SitExtent { len: 0, color, end_insert: None }))
 }
@@ -10,6 +10,7 @@ use crate::situation::Horizon;
 use crate::situation::Situation;
 use crate::situation::Transition;
 use crate::situation::WhatNow;
+use crate::situation::alt;
 use crate::situation::flush;
 use crate::situation::pop;
 use crate::situation::push;
@@ -18,6 +19,8 @@ use crate::situation::COLOR_VAR;
 use crate::situation::COLOR_LVAL;
 use crate::situation::COLOR_NORMAL;
 
+use crate::rule::Rule;
+
 use crate::microparsers::identifierlen;
 use crate::microparsers::is_identifiertail;
 use crate::microparsers::is_whitespace;
@@ -91,7 +94,7 @@ impl Situation for SitVarIdentNecessarilyArray {
 		for (i, &a) in horizon.input.iter().enumerate() {
 			// An identifierhead is also an identifiertail.
 			if !is_identifiertail(a) {
-				return pop(i, 0, Some(b"[@]}\""));
+				return pop(i, 0, Some(alt(Rule::ForInArray, b"[@]}\"")));
 			}
 		}
 		flush(horizon.input.len())
@@ -123,7 +126,7 @@ fn push_forin(pre: usize) -> WhatNow {
 
 fn become_for_in_necessarily_array(pre: usize) -> WhatNow {
 	WhatNow {
-		transform: (pre, 1, Some(b"\"${")),
+		transform: (pre, 1, Some(alt(Rule::ForInArray, b"\"${"))),
 		transition: Transition::Replace(Box::new(SitVarIdentNecessarilyArray {})),
 	}
 }
@@ -175,7 +178,7 @@ fn test_sit_varidentnecessarilyarray() {
 
 	sit_expect!(subj(), b"", &flush(0));
 	sit_expect!(subj(), b"x", &flush(1));
-	sit_expect!(subj(), b"x\n", &pop(1, 0, Some(b"[@]}\"")));
+	sit_expect!(subj(), b"x\n", &pop(1, 0, Some(alt(Rule::ForInArray, b"[@]}\""))));
 }
 
 #[test]
@@ -66,6 +66,8 @@ use crate::sitmagic::push_magic;
 use crate::situation::push;
 #[cfg(test)]
 use crate::situation::COLOR_ESC;
+#[cfg(test)]
+use crate::situation::COLOR_VAR;
 
 #[test]
 fn test_sit_strdq() {
@@ -81,5 +83,6 @@ fn test_sit_strdq() {
 	sit_expect!(SitStrDq::new(), b"$(", &flush(0), &found_cmdsub);
 	sit_expect!(SitStrDq::new(), b"$( ", &found_cmdsub);
 	sit_expect!(SitStrDq::new(), b"$((", &push_magic(0, 2, b')'));
+	sit_expect!(SitStrDq::new(), b"$1", &flush(0), &push_extent(COLOR_VAR, 0, 2));
 	sit_expect!(SitStrDq::new(), b"\\", &push_extent(COLOR_ESC, 0, 2));
 }
@@ -10,9 +10,12 @@ use crate::situation::Horizon;
 use crate::situation::Situation;
 use crate::situation::Transition;
 use crate::situation::WhatNow;
+use crate::situation::alt;
 use crate::situation::flush;
 use crate::situation::pop;
 
+use crate::rule::Rule;
+
 use crate::commonstrcmd::QuotingCtx;
 use crate::commonstrcmd::CommonStrCmdResult;
 use crate::commonstrcmd::common_str_cmd;
@@ -24,6 +27,8 @@ use crate::sitstrdq::SitStrDq;
 
 pub struct SitStrPhantom {
 	pub cmd_end_trigger: u16,
+	// That the quotes are added for.
+	pub rule: Rule,
 }
 
 impl Situation for SitStrPhantom {
@@ -36,7 +41,7 @@ impl Situation for SitStrPhantom {
 		} else if u16::from(horizon.input[mouthful]) != self.cmd_end_trigger {
 			match horizon.input[mouthful] {
 				b'\"' => {
-					return become_real(mouthful);
+					return become_real(self.rule, mouthful);
 				}
 				b'$' | b'`' => {
 					match common_str_cmd(horizon, mouthful, QuotingCtx::Need) {
@@ -60,7 +65,7 @@ impl Situation for SitStrPhantom {
 				_ => {}
 			}
 		}
-		dutifully_end_the_string()
+		dutifully_end_the_string(self.rule)
 	}
 	fn get_color(&self) -> u32 {
 		0x00_ff0000
@@ -72,15 +77,15 @@ fn is_phantomstringfood(c: u8) -> bool {
 	&& c != b'?' && c != b'\\'
 }
 
-fn become_real(pre: usize) -> WhatNow {
+fn become_real(rule: Rule, pre: usize) -> WhatNow {
 	WhatNow {
-		transform: (pre, 1, Some(b"")),
+		transform: (pre, 1, Some(alt(rule, b""))),
 		transition: Transition::Replace(Box::new(SitStrDq::new())),
 	}
 }
 
-fn dutifully_end_the_string() -> WhatNow {
-	pop(0, 0, Some(b"\""))
+fn dutifully_end_the_string(rule: Rule) -> WhatNow {
+	pop(0, 0, Some(alt(rule, b"\"")))
 }
 
 #[cfg(test)]
@@ -96,12 +101,12 @@ use crate::situation::push;
 
 #[cfg(test)]
 fn subject() -> SitStrPhantom {
-	SitStrPhantom{cmd_end_trigger: 0}
+	SitStrPhantom{cmd_end_trigger: 0, rule: Rule::Quote}
 }
 
 #[test]
 fn test_sit_strphantom() {
-	let cod = dutifully_end_the_string();
+	let cod = dutifully_end_the_string(Rule::Quote);
 	let found_cmdsub = push(
 		(0, 2, None),
 		Box::new(SitNormal {
@@ -114,7 +119,7 @@ fn test_sit_strphantom() {
 	sit_expect!(subject(), b" ", &cod);
 	sit_expect!(subject(), b"\\", &cod);
 	sit_expect!(subject(), b"\'", &cod);
-	sit_expect!(subject(), b"\"", &become_real(0));
+	sit_expect!(subject(), b"\"", &become_real(Rule::Quote, 0));
 	sit_expect!(subject(), b"$", &flush(0), &cod);
 	sit_expect!(subject(), b"$(", &flush(0), &found_cmdsub);
 	sit_expect!(subject(), b"a$", &flush(0), &cod);
@@ -7,6 +7,7 @@
  */
 
 use crate::situation::COLOR_NORMAL;
+use crate::situation::Alt;
 use crate::situation::Horizon;
 use crate::situation::Situation;
 use crate::situation::Transition;
@@ -14,10 +15,14 @@ use crate::situation::WhatNow;
 use crate::situation::flush;
 use crate::situation::flush_or_pop;
 use crate::situation::push;
+use crate::situation::alt;
 use crate::situation::COLOR_CMD;
 
+use crate::rule::Rule;
+
 use crate::commonargcmd::common_arg;
 use crate::commonargcmd::common_token;
+use crate::commonargcmd::common_token_quoted_by;
 use crate::machine::expression_tracker;
 use crate::microparsers::is_word;
 use crate::microparsers::prefixlen;
@@ -34,17 +39,28 @@ impl Situation for SitTest {
 			let is_emptystringtest = prefixlen(horizon.input, b"-z ") == 3;
 			let is_nonemptystringtest = prefixlen(horizon.input, b"-n ") == 3;
 			if is_emptystringtest || is_nonemptystringtest {
-				let suggest = common_token(self.end_trigger, horizon, 3);
-				if let Some(ref exciting) = suggest {
-					if let Transition::Push(_) = &exciting.transition {
-						let end_replace: &'static [u8] = if is_emptystringtest {
-							b" = \"\""
-						} else {
-							b" != \"\""
-						};
-						return push_hiddentest(suggest, end_replace, self.end_trigger);
-					} else if horizon.is_lengthenable {
-						return flush(0);
+				// Unquoted, -n is always true, so the quotes change what it does.
+				let rule = if is_nonemptystringtest { Rule::TestNUnquoted } else { Rule::Quote };
+				let suggest = common_token_quoted_by(rule, self.end_trigger, horizon, 3);
+				let end_replace: &'static [u8] = if is_emptystringtest {
+					b" = \"\""
+				} else {
+					b" != \"\""
+				};
+				if let Some(mut exciting) = suggest {
+					match exciting.transition {
+						Transition::Push(_) => {
+							return push_hiddentest(exciting, end_replace, self.end_trigger);
+						}
+						// With --unbreak, the operand is fixed, and then tested like any other.
+						Transition::Err(ref mut e) if e.fix.is_some() && !horizon.is_lengthenable => {
+							e.fix = e.fix.take().map(|(fixrule, fix)| {
+								(fixrule, Box::new(push_hiddentest(*fix, end_replace, self.end_trigger)))
+							});
+							return exciting;
+						}
+						_ if horizon.is_lengthenable => return flush(0),
+						_ => {}
 					}
 				}
 			} else if prefixlen(horizon.input, b"x") == 1 {
@@ -83,7 +99,7 @@ fn become_regular(end_trigger :u16) -> WhatNow {
 }
 
 fn become_regular_with(
-	transform: (usize, usize, Option<&'static [u8]>),
+	transform: (usize, usize, Option<Alt>),
 	end_trigger :u16,
 ) -> WhatNow {
 	WhatNow {
@@ -92,28 +108,32 @@ fn become_regular_with(
 	}
 }
 
+// The comparison is only rewritten if the operand is quoted: Unquoted, it could vanish.
 fn push_hiddentest(
-	inner: Option<WhatNow>,
+	inner: WhatNow,
 	end_replace: &'static [u8],
 	end_trigger: u16,
 ) -> WhatNow {
+	let needs = inner.transform.2.map(|quote| quote.rule);
 	push(
-		(0, 3, Some(b"")),
+		(0, 3, Some(Alt { needs, ..alt(Rule::TestEmptiness, b"") })),
 		Box::new(SitHiddenTest {
-			inner,
+			inner: Some(inner),
 			end_replace,
+			needs,
 			end_trigger,
 		}),
 	)
 }
 
 fn push_xyes(end_trigger: u16) -> WhatNow {
-	push((0, 1, Some(b"")), Box::new(SitXyes { end_trigger }))
+	push((0, 1, Some(alt(Rule::TestXyes, b""))), Box::new(SitXyes { end_trigger }))
 }
 
 struct SitHiddenTest {
 	inner: Option<WhatNow>,
 	end_replace: &'static [u8],
+	needs: Option<Rule>,
 	end_trigger: u16,
 }
 
@@ -124,7 +144,8 @@ impl Situation for SitHiddenTest {
 			exciting.transform.0 = 0;
 			exciting
 		} else {
-			become_regular_with((0, 0, Some(self.end_replace)), self.end_trigger)
+			let replacement = Alt { needs: self.needs, ..alt(Rule::TestEmptiness, self.end_replace) };
+			become_regular_with((0, 0, Some(replacement)), self.end_trigger)
 		}
 	}
 	fn get_color(&self) -> u32 {
@@ -148,7 +169,7 @@ impl Situation for SitXyes {
 				} else if i > 0 || horizon.is_lengthenable {
 					return flush(i);
 				}
-				return become_regular_with((i, 1, Some(replacement)), self.end_trigger);
+				return become_regular_with((i, 1, Some(alt(Rule::TestXyes, replacement))), self.end_trigger);
 			}
 			if let Some(res) = common_arg(self.end_trigger, horizon, i) {
 				return res;
@@ -205,16 +226,20 @@ use crate::situation::pop;
 #[test]
 fn test_sit_test() {
 	let subj = || SitTest { end_trigger: 0u16 };
+	let operand = |quote: Option<Rule>| WhatNow {
+		transform: (3, 0, quote.map(|rule| alt(rule, b"\""))),
+		transition: Transition::Flush,
+	};
 
 	sit_expect!(subj(), b"", &flush(0), &become_regular(0u16));
 
 	sit_expect!(subj(), b"-f $are ", &become_regular(0u16));
-	sit_expect!(subj(), b"-z $are ", &push_hiddentest(None, b"", 0u16));
-	sit_expect!(subj(), b"-n $are ", &push_hiddentest(None, b"", 0u16));
+	sit_expect!(subj(), b"-z $are ", &push_hiddentest(operand(Some(Rule::Quote)), b"", 0u16));
+	sit_expect!(subj(), b"-n $are ", &push_hiddentest(operand(Some(Rule::TestNUnquoted)), b"", 0u16));
 	sit_expect!(subj(), b"-z justkidding ", &become_regular(0u16));
 	sit_expect!(subj(), b"-n justkidding ", &become_regular(0u16));
-	sit_expect!(subj(), b"-z \"", &push_hiddentest(None, b"", 0u16));
-	sit_expect!(subj(), b"-n \"", &push_hiddentest(None, b"", 0u16));
+	sit_expect!(subj(), b"-z \"", &push_hiddentest(operand(None), b"", 0u16));
+	sit_expect!(subj(), b"-n \"", &push_hiddentest(operand(None), b"", 0u16));
 	sit_expect!(subj(), b"-n \0", &flush(0), &become_regular(0u16));
 
 	sit_expect!(subj(), b"x   ", &become_regular(0u16));
@@ -238,10 +263,10 @@ fn test_sit_xyes() {
 
 	sit_expect!(subj(), b" = ", &flush_or_pop(3));
 	sit_expect!(subj(), b" = x", &flush(3));
-	sit_expect!(subj(), b"x", &flush(0), &become_regular_with((0, 1, Some(b"\"\"")), 0u16));
-	sit_expect!(subj(), b" = x ", &become_regular_with((3, 1, Some(b"\"\"")), 0u16));
-	sit_expect!(subj(), b" = x;", &become_regular_with((3, 1, Some(b"\"\"")), 0u16));
-	sit_expect!(subj(), b" = xx", &become_regular_with((3, 1, Some(b"")), 0u16));
+	sit_expect!(subj(), b"x", &flush(0), &become_regular_with((0, 1, Some(alt(Rule::TestXyes, b"\"\""))), 0u16));
+	sit_expect!(subj(), b" = x ", &become_regular_with((3, 1, Some(alt(Rule::TestXyes, b"\"\""))), 0u16));
+	sit_expect!(subj(), b" = x;", &become_regular_with((3, 1, Some(alt(Rule::TestXyes, b"\"\""))), 0u16));
+	sit_expect!(subj(), b" = xx", &become_regular_with((3, 1, Some(alt(Rule::TestXyes, b""))), 0u16));
 }
 
 #[test]
@@ -6,21 +6,55 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/.
  */
 
+use crate::rule::Rule;
+
 #[derive(Copy)]
 #[derive(Clone)]
 pub struct Horizon<'a>{
 	pub input: &'a [u8],
 	pub is_lengthenable: bool,
+	// Whether the input ends with the horizon. Not lengthenable either, then.
+	pub is_eof: bool,
 }
 
 pub trait Situation {
 	fn whatnow(&mut self, horizon: Horizon) -> WhatNow;
 	fn get_color(&self) -> u32;
+	// What a comment says to shellharden, when popped. None for other situations.
+	fn directive(&mut self) -> Option<Directive> {
+		None
+	}
+}
+
+// Rules to disable, from a comment like "# shellharden disable=quote-var".
+#[derive(Default)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub struct Directive {
+	// For the next command.
+	pub next: Vec<Rule>,
+	// For the rest of the file.
+	pub file: Vec<Rule>,
+	// What is wrong with it, by where and how long, in the comment text.
+	pub problems: Vec<(usize, usize, Problem)>,
+	// Of the comment text, to find it from its end.
+	pub len: usize,
+}
+
+#[derive(Clone, Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum Problem {
+	UnknownRule,
+	NoEffect,
 }
 
 pub struct UnsupportedSyntax {
+	pub id: &'static str,
 	pub typ: &'static str,
 	pub msg: &'static str,
+	// For constructs that never work: What to do instead with --unbreak, under which rule.
+	pub fix: Option<(Rule, Box<WhatNow>)>,
 }
 
 pub enum Transition {
@@ -32,8 +66,22 @@ pub enum Transition {
 	Err(UnsupportedSyntax),
 }
 
+#[derive(Copy)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub struct Alt {
+	pub rule: Rule,
+	pub text: &'static [u8],
+	// The rule of another change that this one makes no sense without.
+	pub needs: Option<Rule>,
+}
+
+pub fn alt(rule: Rule, text: &'static [u8]) -> Alt {
+	Alt { rule, text, needs: None }
+}
+
 pub struct WhatNow {
-	pub transform: (usize, usize, Option<&'static [u8]>), // pre, len, alt
+	pub transform: (usize, usize, Option<Alt>), // pre, len, alt
 	pub transition: Transition,
 }
 
@@ -51,14 +99,14 @@ pub fn flush_or_pop(pre: usize) -> WhatNow {
 	}
 }
 
-pub fn pop(pre: usize, len: usize, alt: Option<&'static [u8]>) -> WhatNow {
+pub fn pop(pre: usize, len: usize, alt: Option<Alt>) -> WhatNow {
 	WhatNow {
 		transform: (pre, len, alt),
 		transition: Transition::Pop,
 	}
 }
 
-pub fn push(transform: (usize, usize, Option<&'static [u8]>), sit: Box<dyn Situation>) -> WhatNow {
+pub fn push(transform: (usize, usize, Option<Alt>), sit: Box<dyn Situation>) -> WhatNow {
 	WhatNow {
 		transform,
 		transition: Transition::Push(sit),
@@ -9,11 +9,14 @@
 use crate::situation::Horizon;
 use crate::situation::Situation;
 use crate::situation::WhatNow;
+use crate::situation::alt;
 use crate::situation::flush;
 use crate::situation::if_needed;
 use crate::situation::pop;
 use crate::situation::COLOR_VAR;
 
+use crate::rule::Rule;
+
 use crate::sitextent::push_replaceable;
 
 #[derive(Clone)]
@@ -53,14 +56,14 @@ impl Situation for SitVarBrace {
 				(State::Name, b'[') => self.state = State::Index,
 				(State::Index, b'*') => {
 					self.state = State::Normal;
-					return push_replaceable(COLOR_VAR, i, 1, Some(b"@"));
+					return push_replaceable(COLOR_VAR, i, 1, Some(alt(Rule::ArrayStar, b"@")));
 				}
 				(State::Normal, b'$') => self.state = State::Dollar,
 				(State::Dollar, b'{') => self.depth += 1,
 				(State::Name | State::Index | State::Normal | State::Dollar, b'}') => {
 					self.depth -= 1;
 					if self.depth == 0 {
-						return pop(i, 1, if_needed(self.end_rm, b""));
+						return pop(i, 1, if_needed(self.end_rm, alt(Rule::VarBrace, b"")));
 					}
 				}
 				(State::Name, _) => self.state = State::Normal,
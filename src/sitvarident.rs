@@ -6,30 +6,29 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/.
  */
 
+use crate::situation::Alt;
 use crate::situation::Horizon;
 use crate::situation::Situation;
-use crate::situation::Transition;
 use crate::situation::WhatNow;
+use crate::situation::flush;
+use crate::situation::pop;
 use crate::situation::COLOR_VAR;
 
 use crate::microparsers::predlen;
 use crate::microparsers::is_identifiertail;
 
 pub struct SitVarIdent {
-	pub end_insert: Option<&'static [u8]>,
+	pub end_insert: Option<Alt>,
 }
 
 impl Situation for SitVarIdent {
 	fn whatnow(&mut self, horizon: Horizon) -> WhatNow {
 		let len = predlen(is_identifiertail, horizon.input);
-		WhatNow {
-			transform: (len, 0, self.end_insert),
-			transition: if len < horizon.input.len() {
-				Transition::Pop
-			} else {
-				Transition::FlushPopOnEof
-			},
+		if len == horizon.input.len() && horizon.is_lengthenable {
+			// The end may be yet to come, after which to insert.
+			return flush(len);
 		}
+		pop(len, 0, self.end_insert)
 	}
 	fn get_color(&self) -> u32 {
 		COLOR_VAR
@@ -31,32 +31,32 @@ fn transition_eq(a: &Transition, b: &Transition) -> bool {
 	match (a, b) {
 		(Flush, Flush) => true,
 		(Flush, _) => {
-			eprintln!("Transition mismatch; Lhs={}", "Flush");
+			eprintln!("Transition mismatch; Lhs=Flush");
 			false
 		}
 		(FlushPopOnEof, FlushPopOnEof) => true,
 		(FlushPopOnEof, _) => {
-			eprintln!("Transition mismatch; Lhs={}", "FlushPopOnEof");
+			eprintln!("Transition mismatch; Lhs=FlushPopOnEof");
 			false
 		}
 		(Replace(a), Replace(b)) => sit_eq(a.as_ref(), b.as_ref()),
 		(Replace(_), _) => {
-			eprintln!("Transition mismatch; Lhs={}", "Replace");
+			eprintln!("Transition mismatch; Lhs=Replace");
 			false
 		}
 		(Push(a), Push(b)) => sit_eq(a.as_ref(), b.as_ref()),
 		(Push(_), _) => {
-			eprintln!("Transition mismatch; Lhs={}", "Push");
+			eprintln!("Transition mismatch; Lhs=Push");
 			false
 		}
 		(Pop, Pop) => true,
 		(Pop, _) => {
-			eprintln!("Transition mismatch; Lhs={}", "Pop");
+			eprintln!("Transition mismatch; Lhs=Pop");
 			false
 		}
 		(Transition::Err(_), Transition::Err(_)) => true,
 		(Transition::Err(_), _) => {
-			eprintln!("Transition mismatch; Lhs={}", "Err");
+			eprintln!("Transition mismatch; Lhs=Err");
 			false
 		}
 	}
@@ -74,11 +74,11 @@ fn sit_eq(a: &dyn Situation, b: &dyn Situation) -> bool {
 
 macro_rules! sit_expect {
 	($sit:expr, $inputhorizon:expr, $expect_mid:expr, $expect_eof:expr) => {
-		assert!(whatnow_eq($inputhorizon.len(), &$sit.whatnow(Horizon{input: $inputhorizon, is_lengthenable: true}), $expect_mid));
-		assert!(whatnow_eq($inputhorizon.len(), &$sit.whatnow(Horizon{input: $inputhorizon, is_lengthenable: false}), $expect_eof));
+		assert!(whatnow_eq($inputhorizon.len(), &$sit.whatnow(Horizon{input: $inputhorizon, is_lengthenable: true, is_eof: false}), $expect_mid));
+		assert!(whatnow_eq($inputhorizon.len(), &$sit.whatnow(Horizon{input: $inputhorizon, is_lengthenable: false, is_eof: true}), $expect_eof));
 	};
 	($sit:expr, $inputhorizon:expr, $expect_same:expr) => {
-		assert!(whatnow_eq($inputhorizon.len(), &$sit.whatnow(Horizon{input: $inputhorizon, is_lengthenable: true}), $expect_same));
-		assert!(whatnow_eq($inputhorizon.len(), &$sit.whatnow(Horizon{input: $inputhorizon, is_lengthenable: false}), $expect_same));
+		assert!(whatnow_eq($inputhorizon.len(), &$sit.whatnow(Horizon{input: $inputhorizon, is_lengthenable: true, is_eof: false}), $expect_same));
+		assert!(whatnow_eq($inputhorizon.len(), &$sit.whatnow(Horizon{input: $inputhorizon, is_lengthenable: false, is_eof: true}), $expect_same));
 	};
 }
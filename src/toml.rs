@@ -0,0 +1,257 @@
+/*
+ * Copyright 2024 Andreas Nordal
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+// Enough TOML for configuration files: Tables, arrays of tables, and
+// strings, integers, booleans and arrays as values.
+// Not supported: Dotted keys, inline tables, floats, dates and multi-line strings.
+
+use crate::json::Value;
+
+// On failure: The line number and what is wrong.
+pub fn parse(text: &[u8]) -> Result<Value, (usize, &'static str)> {
+	let mut p = Parser{text, pos: 0, line: 1};
+	p.document().map_err(|msg| (p.line, msg))
+}
+
+struct Parser<'a> {
+	text: &'a [u8],
+	pos: usize,
+	line: usize,
+}
+
+impl<'a> Parser<'a> {
+	fn peek(&self) -> Option<u8> {
+		self.text.get(self.pos).copied()
+	}
+	fn skip_space(&mut self) {
+		while let Some(b' ' | b'\t') = self.peek() {
+			self.pos += 1;
+		}
+	}
+	// Whitespace, comments and newlines.
+	fn skip_blank(&mut self) {
+		loop {
+			self.skip_space();
+			match self.peek() {
+				Some(b'#') => {
+					while !matches!(self.peek(), None | Some(b'\n')) {
+						self.pos += 1;
+					}
+				}
+				Some(b'\r') => self.pos += 1,
+				Some(b'\n') => {
+					self.pos += 1;
+					self.line += 1;
+				}
+				_ => break,
+			}
+		}
+	}
+	fn end_of_line(&mut self) -> Result<(), &'static str> {
+		self.skip_space();
+		match self.peek() {
+			None | Some(b'\n' | b'\r' | b'#') => Ok(()),
+			_ => Err("Expected the end of the line"),
+		}
+	}
+	fn expect(&mut self, s: &[u8]) -> Result<(), &'static str> {
+		if !self.text[self.pos ..].starts_with(s) {
+			return Err(match s {
+				b"=" => "Expected =",
+				_ => "Expected ]",
+			});
+		}
+		self.pos += s.len();
+		Ok(())
+	}
+
+	fn document(&mut self) -> Result<Value, &'static str> {
+		let mut root: Vec<(String, Value)> = Vec::new();
+		// Where key-value pairs go: The root, or the last table of this name.
+		let mut table: Option<String> = None;
+		loop {
+			self.skip_blank();
+			match self.peek() {
+				None => break,
+				Some(b'[') => {
+					let is_array = self.text[self.pos ..].starts_with(b"[[");
+					self.pos += if is_array { 2 } else { 1 };
+					self.skip_space();
+					let name = self.key()?;
+					self.skip_space();
+					self.expect(if is_array { b"]]" } else { b"]" })?;
+					self.end_of_line()?;
+					match root.iter_mut().find(|(k, _)| *k == name) {
+						Some((_, Value::Array(tables))) if is_array => tables.push(Value::Object(Vec::new())),
+						Some(_) => return Err("Duplicate table"),
+						None if is_array => root.push((name.clone(), Value::Array(vec![Value::Object(Vec::new())]))),
+						None => root.push((name.clone(), Value::Object(Vec::new()))),
+					}
+					table = Some(name);
+				}
+				Some(_) => {
+					let key = self.key()?;
+					self.skip_space();
+					self.expect(b"=")?;
+					self.skip_space();
+					let value = self.value()?;
+					self.end_of_line()?;
+					let members = members_of(&mut root, &table);
+					if members.iter().any(|(k, _)| *k == key) {
+						return Err("Duplicate key");
+					}
+					members.push((key, value));
+				}
+			}
+		}
+		Ok(Value::Object(root))
+	}
+
+	fn key(&mut self) -> Result<String, &'static str> {
+		match self.peek() {
+			Some(b'"' | b'\'') => self.string(),
+			_ => {
+				let begin = self.pos;
+				while let Some(b'A' ..= b'Z' | b'a' ..= b'z' | b'0' ..= b'9' | b'_' | b'-') = self.peek() {
+					self.pos += 1;
+				}
+				if self.pos == begin {
+					return Err("Expected a key");
+				}
+				Ok(String::from_utf8(self.text[begin .. self.pos].to_owned()).unwrap())
+			}
+		}
+	}
+
+	fn value(&mut self) -> Result<Value, &'static str> {
+		match self.peek() {
+			Some(b'"' | b'\'') => Ok(Value::Str(self.string()?)),
+			Some(b'[') => {
+				self.pos += 1;
+				let mut elements = Vec::new();
+				loop {
+					self.skip_blank();
+					if self.peek() == Some(b']') {
+						self.pos += 1;
+						return Ok(Value::Array(elements));
+					}
+					elements.push(self.value()?);
+					self.skip_blank();
+					match self.peek() {
+						Some(b',') => self.pos += 1,
+						Some(b']') => {}
+						_ => return Err("Expected , or ]"),
+					}
+				}
+			}
+			_ => {
+				let begin = self.pos;
+				while let Some(b'A' ..= b'Z' | b'a' ..= b'z' | b'0' ..= b'9' | b'_' | b'-' | b'+') = self.peek() {
+					self.pos += 1;
+				}
+				let word = &self.text[begin .. self.pos];
+				match word {
+					b"true" => Ok(Value::Bool(true)),
+					b"false" => Ok(Value::Bool(false)),
+					_ => {
+						let digits: String = word.iter().filter(|&&c| c != b'_').map(|&c| char::from(c)).collect();
+						digits.parse::<i64>().map(|n| Value::Number(n as f64)).map_err(|_| "Expected a value")
+					}
+				}
+			}
+		}
+	}
+
+	// Called at the opening quote.
+	fn string(&mut self) -> Result<String, &'static str> {
+		let quote = self.text[self.pos];
+		self.pos += 1;
+		let mut s = Vec::<u8>::new();
+		loop {
+			let c = match self.peek() {
+				None | Some(b'\n') => return Err("Unterminated string"),
+				Some(c) => c,
+			};
+			self.pos += 1;
+			if c == quote {
+				break;
+			}
+			if c != b'\\' || quote == b'\'' {
+				s.push(c);
+				continue;
+			}
+			let esc = self.peek().ok_or("Unterminated string")?;
+			self.pos += 1;
+			match esc {
+				b'"' | b'\\' => s.push(esc),
+				b'b' => s.push(8),
+				b'f' => s.push(12),
+				b'n' => s.push(b'\n'),
+				b'r' => s.push(b'\r'),
+				b't' => s.push(b'\t'),
+				b'u' | b'U' => {
+					let len = if esc == b'u' { 4 } else { 8 };
+					let hex = self.text.get(self.pos .. self.pos + len).ok_or("Invalid escape")?;
+					let hex = std::str::from_utf8(hex).map_err(|_| "Invalid escape")?;
+					let code = u32::from_str_radix(hex, 16).map_err(|_| "Invalid escape")?;
+					let c = char::from_u32(code).ok_or("Invalid escape")?;
+					self.pos += len;
+					let mut utf8 = [0; 4];
+					s.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+				}
+				_ => return Err("Invalid escape"),
+			}
+		}
+		String::from_utf8(s).map_err(|_| "Invalid UTF-8")
+	}
+}
+
+fn members_of<'a>(root: &'a mut Vec<(String, Value)>, table: &Option<String>) -> &'a mut Vec<(String, Value)> {
+	let name = match table {
+		Some(name) => name,
+		None => return root,
+	};
+	let value = &mut root.iter_mut().find(|(k, _)| k == name).unwrap().1;
+	let value = match value {
+		Value::Array(tables) => tables.last_mut().unwrap(),
+		value => value,
+	};
+	match value {
+		Value::Object(members) => members,
+		_ => unreachable!(),
+	}
+}
+
+#[test]
+fn test_parse() {
+	let value = parse(b"\
+		# comment\n\
+		mode = \"check\" # comment\n\
+		n = 1_000\n\
+		list = [\n  'a\\\\b',\n  \"\\u00e6\", # comment\n]\n\
+		[[override]]\n\
+		path = \"x\"\n\
+		[[override]]\n\
+		path = \"y\"\n\
+		[table]\n\
+		\"quoted key\" = false\n\
+	").ok().unwrap();
+	assert_eq!(value.get("mode").as_str(), Some("check"));
+	assert_eq!(value.get("n").as_uint(), Some(1000));
+	let list = value.get("list").as_array();
+	assert_eq!(list[0].as_str(), Some("a\\\\b"));
+	assert_eq!(list[1].as_str(), Some("æ"));
+	let overrides = value.get("override").as_array();
+	assert_eq!(overrides[1].get("path").as_str(), Some("y"));
+	assert!(matches!(value.get("table").get("quoted key"), Value::Bool(false)));
+
+	assert_eq!(parse(b"a = 1\na = 2\n").err(), Some((2, "Duplicate key")));
+	assert_eq!(parse(b"a = \"x\ny\"\n").err(), Some((1, "Unterminated string")));
+	assert_eq!(parse(b"\n\n[a\n").err(), Some((3, "Expected ]")));
+	assert_eq!(parse(b"a = 1 b\n").err(), Some((1, "Expected the end of the line")));
+}
@@ -0,0 +1,113 @@
+/*
+ * Copyright 2024 Andreas Nordal
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+
+use crate::ignore::Filter;
+
+// A script, or a path that could not be looked into.
+pub type Found = Result<OsString, (OsString, io::Error)>;
+
+// The shell scripts in a directory tree, in sorted order.
+// Version control directories and symlinked directories are skipped,
+// and so is whatever the filter excludes.
+pub fn find_scripts(dir: &OsString, filter: &mut Filter) -> Vec<Found> {
+	let mut found = Vec::new();
+	walk(Path::new(dir), filter, &mut found);
+	found
+}
+
+fn walk(dir: &Path, filter: &mut Filter, found: &mut Vec<Found>) {
+	let entries = fs::read_dir(dir).and_then(|entries| entries.collect::<io::Result<Vec<_>>>());
+	let mut entries = match entries {
+		Ok(entries) => entries,
+		Err(e) => {
+			found.push(Err((dir.as_os_str().to_owned(), e)));
+			return;
+		}
+	};
+	entries.sort_by_key(|entry| entry.file_name());
+
+	for entry in entries {
+		let path = entry.path();
+		let is_file = match entry.file_type() {
+			Ok(t) if t.is_dir() => {
+				if entry.file_name() != ".git" && !filter.is_excluded(&path, true) {
+					walk(&path, filter, found);
+				}
+				continue;
+			}
+			// Whatever it points to, unless that is a directory.
+			Ok(t) if t.is_symlink() => fs::metadata(&path).map(|m| m.is_file()).unwrap_or(false),
+			Ok(t) => t.is_file(),
+			Err(e) => {
+				found.push(Err((path.into_os_string(), e)));
+				continue;
+			}
+		};
+		if !is_file || filter.is_excluded(&path, false) {
+			continue;
+		}
+		let picked = if filter.has_includes() {
+			Ok(filter.is_included(&path))
+		} else {
+			is_script(&path)
+		};
+		match picked {
+			Ok(true) => found.push(Ok(path.into_os_string())),
+			Ok(false) => {}
+			Err(e) => found.push(Err((path.into_os_string(), e))),
+		}
+	}
+}
+
+// By extension, or by shebang if there is no extension.
+fn is_script(path: &Path) -> io::Result<bool> {
+	if let Some(ext) = path.extension() {
+		return Ok(ext == "sh" || ext == "bash");
+	}
+	let mut head = Vec::with_capacity(128);
+	fs::File::open(path)?.take(128).read_to_end(&mut head)?;
+	Ok(is_shell_shebang(&head))
+}
+
+fn is_shell_shebang(head: &[u8]) -> bool {
+	if !head.starts_with(b"#!") {
+		return false;
+	}
+	let line = head[2 ..].split(|&c| c == b'\n').next().unwrap();
+	let mut words = line.split(|&c| c == b' ' || c == b'\t' || c == b'\r').filter(|w| !w.is_empty());
+	let mut interpreter = words.next().map(basename);
+	if interpreter == Some(&b"env"[..]) {
+		interpreter = words.find(|w| !w.starts_with(b"-")).map(basename);
+	}
+	matches!(interpreter, Some(b"sh") | Some(b"bash"))
+}
+
+fn basename(path: &[u8]) -> &[u8] {
+	match path.iter().rposition(|&c| c == b'/') {
+		Some(slash) => &path[slash + 1 ..],
+		None => path,
+	}
+}
+
+#[test]
+fn test_is_shell_shebang() {
+	assert!(is_shell_shebang(b"#!/bin/sh\necho"));
+	assert!(is_shell_shebang(b"#! /usr/bin/env bash\r\n"));
+	assert!(is_shell_shebang(b"#!/usr/bin/env -S bash -e\n"));
+	assert!(is_shell_shebang(b"#!/bin/bash"));
+	assert!(!is_shell_shebang(b"#!/usr/bin/env python3\n"));
+	assert!(!is_shell_shebang(b"#!/bin/zsh\n"));
+	assert!(!is_shell_shebang(b"# /bin/sh\n"));
+	assert!(!is_shell_shebang(b""));
+}
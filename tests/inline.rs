@@ -0,0 +1,13 @@
+use std::process::Command;
+
+// A -c script seldom ends with a newline, which must not make a difference.
+#[test]
+fn inline_script() {
+	let output = Command::new(env!("CARGO_BIN_EXE_shellharden"))
+		.args(["--transform", "-c", "echo $a ${b} `pwd`"])
+		.output()
+		.expect("shellharden: Command not found")
+	;
+	assert_eq!(String::from_utf8_lossy(&output.stdout), "echo \"$a\" \"$b\" \"$PWD\"");
+	assert!(output.status.success());
+}
@@ -1,3 +1,6 @@
+// The test predates these lints.
+#![allow(clippy::match_ref_pats, clippy::assertions_on_constants)]
+
 use std::env;
 use std::process;
 use std::process::Command;
@@ -0,0 +1,23 @@
+use std::process::Command;
+
+// All sources are treated, and the worst outcome decides the exit status.
+#[test]
+fn summary() {
+	let output = Command::new(env!("CARGO_BIN_EXE_shellharden"))
+		.args(["--check", "-c", "echo $a", "-c", "echo $a $10", "-c", "echo \"$a\""])
+		.output()
+		.expect("shellharden: Command not found")
+	;
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.starts_with("-c:1:9: Unsupported syntax: Syntactic pitfall\n"));
+	assert!(stderr.ends_with("Treated 3 files: 2 with changes, 1 with syntax errors, 0 with I/O errors.\n"));
+	assert!(output.stdout.is_empty());
+	assert_eq!(output.status.code(), Some(1));
+
+	let output = Command::new(env!("CARGO_BIN_EXE_shellharden"))
+		.args(["--check", "-c", "echo \"$a\"", "-c", "echo $a"])
+		.output()
+		.expect("shellharden: Command not found")
+	;
+	assert_eq!(output.status.code(), Some(2));
+}